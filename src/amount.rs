@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Result};
+use serde::de::Error as _;
+use std::path::PathBuf;
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const SOL_DECIMALS: usize = 9;
+
+#[derive(Debug, Clone)]
+pub enum Amount {
+    Lamports(u64),
+    Sol(String),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    Nearest,
+    #[default]
+    Reject,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(untagged)]
+enum RawAmount {
+    Integer(u64),
+    Text(String),
+}
+
+impl<'de> serde::Deserialize<'de> for Amount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawAmount::deserialize(deserializer)? {
+            RawAmount::Integer(lamports) => Ok(Amount::Lamports(lamports)),
+            RawAmount::Text(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+// The text form of the amount setting: integer lamports, a "0.1 SOL" decimal or "file:<path>".
+impl std::str::FromStr for Amount {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.strip_prefix("file:") {
+            Some(path) => Ok(Amount::File(PathBuf::from(path))),
+            None => match text.trim().parse::<u64>() {
+                Ok(lamports) => Ok(Amount::Lamports(lamports)),
+                Err(_) => {
+                    parse_amount(text, Rounding::Floor).map(|_| Amount::Sol(text.to_string()))
+                }
+            },
+        }
+    }
+}
+
+impl serde::Serialize for Amount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Amount::Lamports(lamports) => serializer.serialize_u64(*lamports),
+            Amount::Sol(text) => serializer.serialize_str(text),
+            Amount::File(path) => serializer.serialize_str(&format!("file:{}", path.display())),
+        }
+    }
+}
+
+impl Amount {
+    pub fn resolve(&self, rounding: Rounding) -> Result<u64> {
+        match self {
+            Amount::Lamports(lamports) => Ok(*lamports),
+            Amount::Sol(text) => parse_amount(text, rounding),
+            Amount::File(path) => {
+                let content = std::fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Failed to read amount from {}: {}", path.display(), e))?;
+                parse_amount(&content, rounding)
+                    .map_err(|e| anyhow!("Invalid amount in {}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+pub fn parse_amount(text: &str, rounding: Rounding) -> Result<u64> {
+    let text = text.trim();
+    let sol = text
+        .strip_suffix("SOL")
+        .or_else(|| text.strip_suffix("sol"))
+        .map(str::trim);
+
+    match sol {
+        Some(sol) => parse_sol(sol, rounding),
+        None => text.parse::<u64>().map_err(|_| {
+            anyhow!(
+                "expected integer lamports or decimal SOL string like \"0.1 SOL\", got '{}'",
+                text
+            )
+        }),
+    }
+}
+
+// Bare decimal SOL with no unit, locale or grouping: the form parse_sol_arg accepts back.
+pub fn sol_decimal(lamports: u64) -> String {
+    let whole = lamports / LAMPORTS_PER_SOL;
+    let fraction = lamports % LAMPORTS_PER_SOL;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:0width$}", fraction, width = SOL_DECIMALS);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+pub fn parse_sol_arg(value: &str) -> Result<u64, String> {
+    parse_sol(value.trim(), Rounding::Reject).map_err(|e| e.to_string())
+}
+
+fn parse_sol(text: &str, rounding: Rounding) -> Result<u64> {
+    let invalid = || anyhow!("invalid SOL amount '{}'", text);
+    let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if !integer
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let (fraction, excess) = fraction.split_at(fraction.len().min(SOL_DECIMALS));
+    let round_up = if excess.bytes().all(|digit| digit == b'0') {
+        false
+    } else {
+        match rounding {
+            Rounding::Floor => false,
+            Rounding::Ceil => true,
+            Rounding::Nearest => excess.as_bytes()[0] >= b'5',
+            Rounding::Reject => {
+                return Err(anyhow!(
+                    "SOL amount '{}' has more than {} decimal places (set rounding to floor, ceil or nearest)",
+                    text,
+                    SOL_DECIMALS
+                ))
+            }
+        }
+    };
+
+    let integer = if integer.is_empty() {
+        0
+    } else {
+        integer.parse::<u64>().map_err(|_| invalid())?
+    };
+    let fraction = format!("{:0<width$}", fraction, width = SOL_DECIMALS)
+        .parse::<u64>()
+        .map_err(|_| invalid())?;
+
+    integer
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .and_then(|lamports| lamports.checked_add(round_up as u64))
+        .ok_or_else(|| anyhow!("SOL amount '{}' is too large", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sol_decimal_round_trips_through_parse_sol_arg() {
+        for lamports in [
+            0,
+            1,
+            1_000,
+            LAMPORTS_PER_SOL,
+            1_500_000_000,
+            1_234_567_890_123,
+        ] {
+            assert_eq!(parse_sol_arg(&sol_decimal(lamports)), Ok(lamports));
+        }
+        assert_eq!(sol_decimal(1_500_000_000), "1.5");
+    }
+
+    #[test]
+    fn rounding_mode_applies_only_past_nine_decimals() {
+        assert_eq!(
+            parse_amount("0.1 SOL", Rounding::Reject).unwrap(),
+            100_000_000
+        );
+        assert_eq!(
+            parse_amount("1.0000000010 SOL", Rounding::Reject).unwrap(),
+            1_000_000_001
+        );
+        assert!(parse_amount("0.0000000015 SOL", Rounding::Reject).is_err());
+        assert_eq!(
+            parse_amount("0.0000000015 SOL", Rounding::Floor).unwrap(),
+            1
+        );
+        assert_eq!(parse_amount("0.0000000011 SOL", Rounding::Ceil).unwrap(), 2);
+        assert_eq!(
+            parse_amount("0.0000000015 SOL", Rounding::Nearest).unwrap(),
+            2
+        );
+        assert_eq!(
+            parse_amount("0.0000000014 SOL", Rounding::Nearest).unwrap(),
+            1
+        );
+        assert!(parse_amount("18446744074 SOL", Rounding::Floor).is_err());
+    }
+}
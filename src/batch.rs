@@ -0,0 +1,756 @@
+use crate::{
+    error::TransferError,
+    format::{format_sol, render_table},
+    SendOptions, SolanaTransactionManager, TransferCost, TransferSummary,
+};
+use anyhow::{anyhow, Result};
+use log::warn;
+use solana_program::system_instruction;
+use solana_sdk::{
+    instruction::Instruction,
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub struct BatchRecipient {
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub memo: Option<String>,
+}
+
+pub fn load_recipients(path: &str) -> Result<Vec<BatchRecipient>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| TransferError::Config(format!("Failed to read {}: {}", path, e)))?;
+
+    let mut recipients = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("receiver,") {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, ',');
+        let receiver = fields.next().unwrap_or_default().trim();
+        let amount = fields.next().unwrap_or_default().trim();
+        let memo = fields
+            .next()
+            .map(|memo| memo.trim().to_string())
+            .filter(|memo| !memo.is_empty());
+
+        let receiver = Pubkey::from_str(receiver).map_err(|e| {
+            TransferError::Config(format!("{}:{}: invalid receiver: {}", path, index + 1, e))
+        })?;
+        let amount = amount.parse::<u64>().map_err(|e| {
+            TransferError::Config(format!("{}:{}: invalid amount: {}", path, index + 1, e))
+        })?;
+        if amount == 0 {
+            return Err(
+                TransferError::Config(format!("{}:{}: amount is 0", path, index + 1)).into(),
+            );
+        }
+
+        recipients.push(BatchRecipient {
+            receiver,
+            amount,
+            memo,
+        });
+    }
+
+    if recipients.is_empty() {
+        return Err(TransferError::Config(format!("{} has no recipients", path)).into());
+    }
+
+    Ok(recipients)
+}
+
+#[derive(Debug, Clone, Copy, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateRecipients {
+    Error,
+    #[default]
+    Warn,
+    Merge,
+}
+
+fn duplicated_receivers(recipients: &[BatchRecipient]) -> Vec<Pubkey> {
+    let mut seen = HashSet::new();
+    let mut duplicated = Vec::new();
+    for recipient in recipients {
+        if !seen.insert(recipient.receiver) && !duplicated.contains(&recipient.receiver) {
+            duplicated.push(recipient.receiver);
+        }
+    }
+    duplicated
+}
+
+// Sums transfers to the same receiver into the first occurrence; the first memo is kept.
+fn merge_recipients(recipients: Vec<BatchRecipient>) -> Result<Vec<BatchRecipient>> {
+    let mut merged: Vec<BatchRecipient> = Vec::new();
+    for recipient in recipients {
+        match merged
+            .iter_mut()
+            .find(|existing| existing.receiver == recipient.receiver)
+        {
+            Some(existing) => {
+                existing.amount = existing
+                    .amount
+                    .checked_add(recipient.amount)
+                    .ok_or_else(|| anyhow!("Merged amount for {} overflows", recipient.receiver))?;
+                if existing.memo.is_none() {
+                    existing.memo = recipient.memo;
+                }
+            }
+            None => merged.push(recipient),
+        }
+    }
+    Ok(merged)
+}
+
+fn recipient_instructions(sender: &Pubkey, recipient: &BatchRecipient) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    if let Some(memo) = &recipient.memo {
+        instructions.push(spl_memo::build_memo(memo.as_bytes(), &[sender]));
+    }
+    instructions.push(system_instruction::transfer(
+        sender,
+        &recipient.receiver,
+        recipient.amount,
+    ));
+    instructions
+}
+
+fn transaction_size(sender: &Pubkey, instructions: &[Instruction]) -> Result<usize> {
+    let transaction = Transaction::new_unsigned(Message::new(instructions, Some(sender)));
+    Ok(bincode::serialized_size(&transaction)? as usize)
+}
+
+#[derive(Debug, Default)]
+pub struct PackedTransaction {
+    pub instructions: Vec<Instruction>,
+    pub recipients: Vec<BatchRecipient>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Confirmed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+pub struct RecipientOutcome {
+    pub receiver: String,
+    pub amount: u64,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+pub struct BatchOutcome {
+    pub status: BatchStatus,
+    pub recipients: Vec<RecipientOutcome>,
+    pub amount: u64,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BatchOutcome {
+    fn new(batch: &PackedTransaction) -> Self {
+        BatchOutcome {
+            status: BatchStatus::Confirmed,
+            recipients: batch
+                .recipients
+                .iter()
+                .map(|recipient| RecipientOutcome {
+                    receiver: recipient.receiver.to_string(),
+                    amount: recipient.amount,
+                })
+                .collect(),
+            amount: batch.amount(),
+            signature: None,
+            error: None,
+        }
+    }
+
+    fn confirmed(batch: &PackedTransaction, signature: &Signature) -> Self {
+        BatchOutcome {
+            signature: Some(signature.to_string()),
+            ..BatchOutcome::new(batch)
+        }
+    }
+
+    fn failed(batch: &PackedTransaction, error: &anyhow::Error) -> Self {
+        BatchOutcome {
+            status: BatchStatus::Failed,
+            error: Some(error.to_string()),
+            ..BatchOutcome::new(batch)
+        }
+    }
+}
+
+// Transactions after a stop_on_error abort were never sent.
+fn with_skipped(batches: &[PackedTransaction], outcomes: Vec<BatchOutcome>) -> Vec<BatchOutcome> {
+    let skipped = batches[outcomes.len()..].iter().map(|batch| BatchOutcome {
+        status: BatchStatus::Skipped,
+        ..BatchOutcome::new(batch)
+    });
+    outcomes.into_iter().chain(skipped).collect()
+}
+
+impl PackedTransaction {
+    pub fn amount(&self) -> u64 {
+        self.recipients
+            .iter()
+            .map(|recipient| recipient.amount)
+            .sum()
+    }
+}
+
+pub fn batch_total(recipients: &[BatchRecipient]) -> Result<u64> {
+    recipients
+        .iter()
+        .try_fold(0u64, |total, recipient| total.checked_add(recipient.amount))
+        .ok_or_else(|| {
+            TransferError::Config("Total amount of the batch overflows".to_string()).into()
+        })
+}
+
+pub fn pack_transactions(
+    sender: &Pubkey,
+    recipients: &[BatchRecipient],
+) -> Result<Vec<PackedTransaction>> {
+    let mut batches: Vec<PackedTransaction> = Vec::new();
+    let mut current = PackedTransaction::default();
+
+    for recipient in recipients {
+        let instructions = recipient_instructions(sender, recipient);
+
+        let mut candidate = current.instructions.clone();
+        candidate.extend(instructions.iter().cloned());
+        if transaction_size(sender, &candidate)? <= PACKET_DATA_SIZE {
+            current.instructions = candidate;
+            current.recipients.push(recipient.clone());
+            continue;
+        }
+
+        if transaction_size(sender, &instructions)? > PACKET_DATA_SIZE {
+            return Err(anyhow!(
+                "Transfer to {} does not fit in a single transaction (memo too long?)",
+                recipient.receiver
+            ));
+        }
+        batches.push(std::mem::replace(
+            &mut current,
+            PackedTransaction {
+                instructions,
+                recipients: vec![recipient.clone()],
+            },
+        ));
+    }
+
+    if !current.instructions.is_empty() {
+        batches.push(current);
+    }
+
+    Ok(batches)
+}
+
+impl SolanaTransactionManager {
+    pub(crate) fn load_batch_recipients(&self, path: &str) -> Result<Vec<BatchRecipient>> {
+        self.handle_duplicate_recipients(path, load_recipients(path)?)
+    }
+
+    pub(crate) fn handle_duplicate_recipients(
+        &self,
+        path: &str,
+        recipients: Vec<BatchRecipient>,
+    ) -> Result<Vec<BatchRecipient>> {
+        let duplicated = duplicated_receivers(&recipients);
+        if duplicated.is_empty() {
+            return Ok(recipients);
+        }
+
+        let list = duplicated
+            .iter()
+            .map(|receiver| receiver.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.config.transaction.duplicate_recipients {
+            DuplicateRecipients::Error => Err(TransferError::Config(format!(
+                "{} lists these receivers more than once: {}",
+                path, list
+            ))
+            .into()),
+            DuplicateRecipients::Warn => {
+                warn!("{} に同じ宛先が複数回あります: {}", path, list);
+                Ok(recipients)
+            }
+            DuplicateRecipients::Merge => {
+                warn!("{} の重複した宛先を1件にまとめます: {}", path, list);
+                merge_recipients(recipients)
+            }
+        }
+    }
+
+    fn prepare_batch(
+        &self,
+        recipients: &[BatchRecipient],
+        options: &SendOptions,
+    ) -> Result<(Box<dyn Signer>, Vec<PackedTransaction>)> {
+        if self.config.token.is_some() {
+            return Err(TransferError::Config(
+                "Batch mode supports SOL transfers only".to_string(),
+            )
+            .into());
+        }
+
+        let sender_keypair = self.create_sender_keypair()?;
+        let sender = sender_keypair.pubkey();
+        for recipient in recipients {
+            self.check_receiver_allowed(&recipient.receiver)?;
+        }
+
+        let total = batch_total(recipients)?;
+        let batches = pack_transactions(&sender, recipients)?;
+        let fees = self.estimate_batch_fees(&sender, &batches)?;
+        let balance = self.get_balance(&sender)?;
+        let required = total
+            .checked_add(fees)
+            .and_then(|required| required.checked_add(self.min_balance(balance)))
+            .ok_or_else(|| {
+                TransferError::Config("Total amount of the batch overflows".to_string())
+            })?;
+        if balance < required {
+            return Err(TransferError::InsufficientBalance { balance, required }.into());
+        }
+        self.check_batch_total(options, &sender, recipients, total, fees)?;
+
+        println!(
+            "{}件の送金 (合計 {}) を{}件のトランザクションで送信します",
+            recipients.len(),
+            format_sol(total),
+            batches.len()
+        );
+
+        Ok((sender_keypair, batches))
+    }
+
+    // max_total_cost, the large-transfer prompt and the approval webhook apply to the batch
+    // total, the same as for a split transfer. The approval summary lists every receiver.
+    fn check_batch_total(
+        &self,
+        options: &SendOptions,
+        sender: &Pubkey,
+        recipients: &[BatchRecipient],
+        total: u64,
+        fees: u64,
+    ) -> Result<()> {
+        self.check_total_cost(&TransferCost {
+            amount: total,
+            fee: fees,
+            account_rent: 0,
+        })?;
+        self.confirm_large_transfer(total, options)?;
+        if let Some(url) = &self.config.transaction.approval_webhook_url {
+            self.request_approval(
+                url,
+                &TransferSummary {
+                    sender: sender.to_string(),
+                    receiver: recipients
+                        .iter()
+                        .map(|recipient| recipient.receiver.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    amount: total,
+                    mint: None,
+                    fee: fees,
+                    label: self.config.transaction.label.clone(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    fn record_batch_receipts(
+        &self,
+        signature: &Signature,
+        sender: &Pubkey,
+        batch: &PackedTransaction,
+        fee: u64,
+    ) {
+        for (position, recipient) in batch.recipients.iter().enumerate() {
+            let summary = TransferSummary {
+                sender: sender.to_string(),
+                receiver: recipient.receiver.to_string(),
+                amount: recipient.amount,
+                mint: None,
+                fee: if position == 0 { fee } else { 0 },
+                label: self.config.transaction.label.clone(),
+            };
+            self.record_receipt(signature, &summary);
+        }
+    }
+
+    fn sign_and_submit_batch(
+        &self,
+        signer: &dyn Signer,
+        batch: &PackedTransaction,
+        deadline: Option<u64>,
+    ) -> Result<(Signature, u64)> {
+        let sender = signer.pubkey();
+        self.check_low_balance(&sender, self.get_balance(&sender)?);
+        let transaction = self.sign_transaction(&batch.instructions, signer)?;
+        let fee = self.client.get_fee_for_message(&transaction.message)?;
+        let signature = self.submit_transaction(&transaction, batch.amount(), deadline)?;
+        Ok((signature, fee))
+    }
+
+    pub fn send_batch(
+        &self,
+        path: &str,
+        options: &SendOptions,
+        concurrent: bool,
+        report: Option<&Path>,
+    ) -> Result<Vec<String>> {
+        let recipients = self.load_batch_recipients(path)?;
+        let outcomes = if concurrent {
+            self.send_recipients_concurrent(&recipients, options)?
+        } else {
+            self.send_recipients(&recipients, options)?
+        };
+        self.finish_batch(outcomes, report)
+    }
+
+    pub(crate) fn send_recipients(
+        &self,
+        recipients: &[BatchRecipient],
+        options: &SendOptions,
+    ) -> Result<Vec<BatchOutcome>> {
+        let deadline = self.submit_deadline()?;
+        let (sender_keypair, batches) = self.prepare_batch(recipients, options)?;
+
+        let mut outcomes = Vec::new();
+        for (index, batch) in batches.iter().enumerate() {
+            match self.sign_and_submit_batch(sender_keypair.as_ref(), batch, deadline) {
+                Ok((signature, fee)) => {
+                    self.print_progress(index, batches.len(), "TX成功!", signature);
+                    self.record_batch_receipts(&signature, &sender_keypair.pubkey(), batch, fee);
+                    self.record_signature(&signature);
+                    outcomes.push(BatchOutcome::confirmed(batch, &signature));
+                }
+                Err(e) => {
+                    self.print_progress(index, batches.len(), "TX失敗", &e);
+                    outcomes.push(BatchOutcome::failed(batch, &e));
+                    if self.config.transaction.stop_on_error {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(with_skipped(&batches, outcomes))
+    }
+
+    pub(crate) fn send_recipients_concurrent(
+        &self,
+        recipients: &[BatchRecipient],
+        options: &SendOptions,
+    ) -> Result<Vec<BatchOutcome>> {
+        let deadline = self.submit_deadline()?;
+        let (sender_keypair, batches) = self.prepare_batch(recipients, options)?;
+
+        let mut submitted = Vec::new();
+        let mut outcomes = Vec::new();
+        for (index, batch) in batches.iter().enumerate() {
+            let broadcast = self
+                .sign_transaction(&batch.instructions, sender_keypair.as_ref())
+                .and_then(|transaction| {
+                    let fee = self.client.get_fee_for_message(&transaction.message)?;
+                    self.check_submit_deadline(deadline)?;
+                    Ok((self.broadcast_transaction(&transaction)?, fee))
+                });
+            match broadcast {
+                Ok(sent) => submitted.push((index, sent)),
+                Err(e) => {
+                    self.print_progress(index, batches.len(), "TX失敗", &e);
+                    outcomes.push((index, BatchOutcome::failed(batch, &e)));
+                    if self.config.transaction.stop_on_error {
+                        break;
+                    }
+                }
+            }
+        }
+        println!(
+            "{}件のトランザクションを送信しました。確認を待っています...",
+            submitted.len()
+        );
+
+        let signatures: Vec<Signature> = submitted
+            .iter()
+            .map(|(_, (signature, _))| *signature)
+            .collect();
+        let confirmations = self.confirm_signatures(&signatures);
+
+        for ((index, (signature, fee)), confirmation) in submitted.iter().zip(confirmations) {
+            let batch = &batches[*index];
+            match confirmation {
+                Ok(()) => {
+                    self.print_progress(*index, batches.len(), "TX成功!", signature);
+                    self.record_batch_receipts(signature, &sender_keypair.pubkey(), batch, *fee);
+                    self.record_signature(signature);
+                    outcomes.push((*index, BatchOutcome::confirmed(batch, signature)));
+                }
+                Err(e) => {
+                    self.print_progress(*index, batches.len(), "TX失敗", &e);
+                    let mut outcome = BatchOutcome::failed(batch, &e);
+                    outcome.signature = Some(signature.to_string());
+                    outcomes.push((*index, outcome));
+                }
+            }
+        }
+        outcomes.sort_by_key(|(index, _)| *index);
+
+        Ok(with_skipped(
+            &batches,
+            outcomes.into_iter().map(|(_, outcome)| outcome).collect(),
+        ))
+    }
+
+    fn print_progress(&self, index: usize, total: usize, result: &str, detail: impl Display) {
+        if !self.config.output.table {
+            println!("[{}/{}] {}: {}", index + 1, total, result, detail);
+        }
+    }
+
+    fn print_batch_table(&self, outcomes: &[BatchOutcome]) {
+        let rows: Vec<Vec<String>> = outcomes
+            .iter()
+            .flat_map(|outcome| {
+                let status = match outcome.status {
+                    BatchStatus::Confirmed => "成功",
+                    BatchStatus::Failed => "失敗",
+                    BatchStatus::Skipped => "未送信",
+                };
+                outcome.recipients.iter().map(move |recipient| {
+                    vec![
+                        recipient.receiver.clone(),
+                        format_sol(recipient.amount),
+                        status.to_string(),
+                        outcome.signature.clone().unwrap_or_default(),
+                    ]
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            render_table(&["受取アドレス", "金額", "状態", "シグネチャ"], &rows)
+        );
+    }
+
+    pub(crate) fn finish_batch(
+        &self,
+        outcomes: Vec<BatchOutcome>,
+        report: Option<&Path>,
+    ) -> Result<Vec<String>> {
+        let count = |status: BatchStatus| {
+            outcomes
+                .iter()
+                .filter(|outcome| outcome.status == status)
+                .count()
+        };
+        let confirmed = count(BatchStatus::Confirmed);
+        if self.config.output.table {
+            self.print_batch_table(&outcomes);
+        }
+        println!(
+            "バッチ結果: 成功 {}件 / 失敗 {}件 / 未送信 {}件",
+            confirmed,
+            count(BatchStatus::Failed),
+            count(BatchStatus::Skipped)
+        );
+        for (index, outcome) in outcomes.iter().enumerate() {
+            let status = match outcome.status {
+                BatchStatus::Confirmed => continue,
+                BatchStatus::Failed => "失敗",
+                BatchStatus::Skipped => "未送信",
+            };
+            println!(
+                "  [{}/{}] {}: {} ({})",
+                index + 1,
+                outcomes.len(),
+                status,
+                outcome
+                    .recipients
+                    .iter()
+                    .map(|recipient| recipient.receiver.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                outcome.error.as_deref().unwrap_or("stop_on_errorで中止")
+            );
+        }
+
+        if let Some(path) = report {
+            std::fs::write(path, serde_json::to_string_pretty(&outcomes)?)?;
+            println!("バッチの結果を書き出しました: {}", path.display());
+        }
+
+        if confirmed < outcomes.len() {
+            return Err(TransferError::PartialBatchFailure {
+                failed: outcomes.len() - confirmed,
+                total: outcomes.len(),
+            }
+            .into());
+        }
+
+        Ok(outcomes
+            .into_iter()
+            .filter_map(|outcome| outcome.signature)
+            .collect())
+    }
+
+    pub(crate) fn estimate_batch_fees(
+        &self,
+        sender: &Pubkey,
+        batches: &[PackedTransaction],
+    ) -> Result<u64> {
+        let blockhash = self.client.get_latest_blockhash()?;
+        let mut total_fee = 0;
+        for batch in batches {
+            let message =
+                Message::new_with_blockhash(&batch.instructions, Some(sender), &blockhash);
+            total_fee += self.client.get_fee_for_message(&message)?;
+        }
+        Ok(total_fee)
+    }
+
+    pub fn simulate_batch(&self, path: &str) -> Result<()> {
+        let sender = self.create_sender_keypair()?.pubkey();
+        let recipients = self.load_batch_recipients(path)?;
+        let batches = pack_transactions(&sender, &recipients)?;
+        let total_fee = self.estimate_batch_fees(&sender, &batches)?;
+
+        let total = batch_total(&recipients)?;
+        let balance = self.get_balance(&sender)?;
+        let required = total
+            .checked_add(total_fee)
+            .and_then(|required| required.checked_add(self.min_balance(balance)))
+            .ok_or_else(|| {
+                TransferError::Config("Total amount of the batch overflows".to_string())
+            })?;
+
+        println!("宛先: {}件", recipients.len());
+        println!("トランザクション数: {}", batches.len());
+        println!("送金額合計: {}", format_sol(total));
+        println!("手数料合計 (見積もり): {}", format_sol(total_fee));
+        println!("必要額 (min_balance込み): {}", format_sol(required));
+        println!("現在の残高: {}", format_sol(balance));
+
+        if balance < required {
+            return Err(TransferError::InsufficientBalance { balance, required }.into());
+        }
+        println!("残高は足りています");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(seed: u8, amount: u64, memo: Option<&str>) -> BatchRecipient {
+        BatchRecipient {
+            receiver: Pubkey::new_from_array([seed; 32]),
+            amount,
+            memo: memo.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn pack_transactions_fills_each_packet_in_order() {
+        let sender = Pubkey::new_from_array([1; 32]);
+        let recipients: Vec<BatchRecipient> = (0..40)
+            .map(|seed| recipient(seed + 2, 1_000 + seed as u64, Some("payout")))
+            .collect();
+        let batches = pack_transactions(&sender, &recipients).unwrap();
+
+        assert!(batches.len() > 1);
+        for batch in &batches {
+            assert!(transaction_size(&sender, &batch.instructions).unwrap() <= PACKET_DATA_SIZE);
+            assert_eq!(batch.instructions.len(), batch.recipients.len() * 2);
+        }
+        let packed: Vec<Pubkey> = batches
+            .iter()
+            .flat_map(|batch| batch.recipients.iter().map(|recipient| recipient.receiver))
+            .collect();
+        let expected: Vec<Pubkey> = recipients
+            .iter()
+            .map(|recipient| recipient.receiver)
+            .collect();
+        assert_eq!(packed, expected);
+        assert_eq!(
+            batches.iter().map(PackedTransaction::amount).sum::<u64>(),
+            batch_total(&recipients).unwrap()
+        );
+    }
+
+    #[test]
+    fn pack_transactions_rejects_a_transfer_too_large_for_a_packet() {
+        let sender = Pubkey::new_from_array([1; 32]);
+        let memo = "x".repeat(PACKET_DATA_SIZE);
+        assert!(pack_transactions(&sender, &[recipient(2, 1, Some(&memo))]).is_err());
+    }
+
+    #[test]
+    fn batch_total_rejects_overflow() {
+        assert_eq!(
+            batch_total(&[recipient(2, 1, None), recipient(3, 2, None)]).unwrap(),
+            3
+        );
+        assert!(batch_total(&[recipient(2, u64::MAX, None), recipient(3, 1, None)]).is_err());
+    }
+
+    #[test]
+    fn load_recipients_rejects_a_zero_amount_row() {
+        let path = std::env::temp_dir().join(format!("batch-zero-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            format!(
+                "receiver,amount\n{},5\n{},0\n",
+                Pubkey::new_from_array([2; 32]),
+                Pubkey::new_from_array([3; 32])
+            ),
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let error = load_recipients(path).unwrap_err().to_string();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(error, format!("Config error: {}:3: amount is 0", path));
+    }
+
+    #[test]
+    fn merge_recipients_sums_duplicates_and_keeps_the_first_memo() {
+        let merged = merge_recipients(vec![
+            recipient(2, 1, None),
+            recipient(3, 5, Some("b")),
+            recipient(2, 2, Some("a")),
+        ])
+        .unwrap();
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].amount, 3);
+        assert_eq!(merged[0].memo.as_deref(), Some("a"));
+        assert_eq!(
+            duplicated_receivers(&[recipient(2, 1, None), recipient(2, 1, None)]).len(),
+            1
+        );
+    }
+}
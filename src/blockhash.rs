@@ -0,0 +1,131 @@
+use crate::{error::TransferError, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use log::info;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, instruction::Instruction, message::Message,
+    pubkey::Pubkey,
+};
+use std::time::{Duration, Instant};
+
+const MAX_BLOCKHASH_REFRESH_SECS: u64 = 60;
+const ESTIMATED_BLOCK_TIME: Duration = Duration::from_millis(400);
+const EXPIRY_MARGIN_BLOCKS: u64 = 30;
+const MAX_DUPLICATE_WAITS: u32 = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CachedBlockhash {
+    blockhash: Hash,
+    block_height: u64,
+    last_valid_block_height: u64,
+    fetched_at: Instant,
+}
+
+impl CachedBlockhash {
+    // Estimates the current block height from the time since the fetch so an expiring blockhash
+    // is replaced before the node would reject it, without a block height query per transaction.
+    fn usable(&self, refresh: Duration) -> bool {
+        let elapsed = self.fetched_at.elapsed();
+        let estimated_height =
+            self.block_height + (elapsed.as_millis() / ESTIMATED_BLOCK_TIME.as_millis()) as u64;
+        elapsed < refresh && estimated_height + EXPIRY_MARGIN_BLOCKS < self.last_valid_block_height
+    }
+}
+
+impl SolanaTransactionManager {
+    fn fetch_blockhash(&self) -> Result<(Hash, u64)> {
+        let (blockhash, last_valid_block_height) = self
+            .client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+        self.remember_blockhash(blockhash, last_valid_block_height);
+        Ok((blockhash, last_valid_block_height))
+    }
+
+    // With blockhash_refresh_secs set, every transaction signed within the refresh interval
+    // shares one blockhash instead of fetching its own.
+    pub(crate) fn signing_blockhash(&self) -> Result<Hash> {
+        let Some(refresh) = self.config.transaction.blockhash_refresh_secs else {
+            return Ok(self.fetch_blockhash()?.0);
+        };
+        if refresh == 0 || refresh > MAX_BLOCKHASH_REFRESH_SECS {
+            return Err(TransferError::Config(format!(
+                "blockhash_refresh_secs must be between 1 and {}, got {}",
+                MAX_BLOCKHASH_REFRESH_SECS, refresh
+            ))
+            .into());
+        }
+
+        let mut cached = self.cached_blockhash.lock().unwrap();
+        if let Some(entry) = cached.filter(|entry| entry.usable(Duration::from_secs(refresh))) {
+            return Ok(entry.blockhash);
+        }
+
+        let (blockhash, last_valid_block_height) = self.fetch_blockhash()?;
+        let block_height = self
+            .client
+            .get_block_height_with_commitment(CommitmentConfig::confirmed())?;
+        info!(
+            "ブロックハッシュを更新しました: {} (有効な最後のブロック高 {})",
+            blockhash, last_valid_block_height
+        );
+        *cached = Some(CachedBlockhash {
+            blockhash,
+            block_height,
+            last_valid_block_height,
+            fetched_at: Instant::now(),
+        });
+        Ok(blockhash)
+    }
+
+    // Builds the message to sign, making sure it differs from every message already signed in
+    // this run. The same instructions over the same blockhash give a byte-identical transaction
+    // that the cluster drops as a duplicate (split chunks, repeated stdin lines, a short
+    // recurring interval), so a newer blockhash is waited for instead. --blockhash cannot change,
+    // so a repeat is refused.
+    pub(crate) fn unique_message(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+    ) -> Result<Message> {
+        let mut blockhash = match self.fixed_blockhash {
+            Some(blockhash) => blockhash,
+            None => self.signing_blockhash()?,
+        };
+        let mut waits = 0;
+        loop {
+            let message = Message::new_with_blockhash(instructions, Some(payer), &blockhash);
+            if self.signed_messages.lock().unwrap().insert(message.hash()) {
+                return Ok(message);
+            }
+            if self.fixed_blockhash.is_some() {
+                return Err(anyhow!(
+                    "An identical transaction was already signed with blockhash {}; it would be dropped as a duplicate",
+                    blockhash
+                ));
+            }
+            if waits == MAX_DUPLICATE_WAITS {
+                return Err(anyhow!(
+                    "No new blockhash after {} attempts; an identical transaction was already signed with {}",
+                    waits,
+                    blockhash
+                ));
+            }
+            info!(
+                "同じ内容のTXをブロックハッシュ {} で署名済みのため、新しいブロックハッシュを待ちます",
+                blockhash
+            );
+            self.invalidate_cached_blockhash(&blockhash);
+            std::thread::sleep(self.poll_interval());
+            waits += 1;
+            blockhash = self.signing_blockhash()?;
+        }
+    }
+
+    // Drops the cached blockhash once it is known to be unusable so the next signature fetches
+    // a fresh one.
+    pub(crate) fn invalidate_cached_blockhash(&self, blockhash: &Hash) {
+        let mut cached = self.cached_blockhash.lock().unwrap();
+        if cached.is_some_and(|entry| entry.blockhash == *blockhash) {
+            *cached = None;
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::amount::parse_sol_arg;
+use crate::format::{Locale, Units};
+use crate::payments::{parse_since, Since};
+use clap::{Parser, Subcommand};
+use solana_sdk::{commitment_config::CommitmentLevel, hash::Hash};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+fn parse_blockhash(value: &str) -> Result<Hash, String> {
+    Hash::from_str(value).map_err(|e| format!("invalid blockhash: {}", e))
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Solana SOL/SPLトークン送金ツール")]
+pub struct Cli {
+    /// 設定ファイル。複数指定すると後のファイルが前のファイルをキー単位で上書きする
+    /// (例: --config base.toml --config devnet.toml)。指定されなかったキーは前のファイルの値が残る。
+    /// http(s)://のURLなら毎回取得する (環境変数SOLANA_TRANSFER_CONFIG_AUTHがあればAuthorizationヘッダーに使う)
+    #[arg(long, global = true, default_value = "config/config.toml")]
+    pub config: Vec<String>,
+
+    /// 送金額が手数料やdust_thresholdを下回っていても送金する
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// 送金前にクラスタの健全性 (直近のTPSが0でない・スロットが進んでいる) を確かめ、停止していそうなら送らない (--forceで続行)
+    #[arg(long, global = true)]
+    pub network_check: bool,
+
+    /// 確認プロンプト (前回の未確認TXを先に確認するか等) にすべて「はい」と答える。
+    /// large_transfer_threshold超過の金額確認は省略しない
+    #[arg(long, global = true)]
+    pub yes: bool,
+
+    /// large_transfer_threshold を超える送金でも金額の入力による確認を省略する
+    #[arg(long, global = true)]
+    pub yes_large: bool,
+
+    /// 送金額0のトランザクションを送ることを許す (手数料だけがかかる)
+    #[arg(long, global = true)]
+    pub allow_zero: bool,
+
+    /// confirmedで送金完了とし、finalizedになるまでの追跡は裏で続ける (結果は最後に表示)
+    #[arg(long, global = true)]
+    pub confirm_finalized_after_return: bool,
+
+    /// ログ行とレシートに付けるラベル (例: payroll)。オンチェーンには載らない。設定ファイルのlabelより優先
+    #[arg(long, global = true)]
+    pub label: Option<String>,
+
+    /// SOL額の表示形式 (小数点と桁区切り)
+    #[arg(long, global = true, value_enum, default_value_t = Locale::Plain)]
+    pub locale: Locale,
+
+    /// 残高・金額の表示単位 (入力の解釈は変わらない)
+    #[arg(long, global = true, value_enum, default_value_t = Units::Sol)]
+    pub units: Units,
+
+    /// 残高の読み取りに使うコミットメント (processed/confirmed/finalized)。送信の確認とは別
+    #[arg(long, global = true)]
+    pub commitment_for_balance: Option<CommitmentLevel>,
+
+    /// 設定ファイルとCLIフラグを反映した最終的な設定をJSONで表示して終了する (秘密鍵は伏せる)
+    #[arg(long, global = true)]
+    pub print_config: bool,
+
+    /// 送金額の代わりに、このバイト数のデータを持つアカウントのレント免除最低額ちょうどを送る
+    #[arg(long, value_name = "BYTES")]
+    pub init_account_space: Option<usize>,
+
+    /// 送金までの各判断 (残高・手数料・受取アカウント・送信と確認) を順に説明しながら実行する
+    #[arg(long, global = true)]
+    pub explain: bool,
+
+    /// 送信する直前 (--dry-run・--simulateでは検証の前) に各命令 (プログラムID・アカウント・データ長) を表示する
+    #[arg(long, global = true)]
+    pub trace_tx: bool,
+
+    /// バッチ・配布の結果を1送金ずつのログではなく表 (受取アドレス・金額・状態・シグネチャ) で表示する
+    #[arg(long, global = true)]
+    pub table: bool,
+
+    /// 実行後のRPCエンドポイントの成績 (成功・失敗回数と平均応答時間) を表ではなくJSON ({"endpoints": [...]}) で表示する
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// 最新のブロックハッシュを取得せず、このブロックハッシュ (base58) で署名する
+    #[arg(long, global = true, value_name = "HASH", value_parser = parse_blockhash)]
+    pub blockhash: Option<Hash>,
+
+    /// 確認できたシグネチャを1行ずつこのファイルに追記する (なければ作成。バッチでは各トランザクション分)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub output_signature_file: Option<String>,
+
+    /// 送金せず、送金前のチェック (残高・受取アカウント・手数料など) をすべて行い、見つかった問題を一覧で表示する
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// --dry-runで見つかった問題をJSONで書き出すパス
+    #[arg(long, value_name = "PATH", requires = "dry_run")]
+    pub dry_run_report: Option<PathBuf>,
+
+    /// 送金せず、署名したトランザクションをsimulateTransactionで検証する。
+    /// 既定ではブロックハッシュを最新に差し替え、署名は検証しない (古いブロックハッシュでも通る)
+    #[arg(long)]
+    pub simulate: bool,
+
+    /// --simulateで署名も検証する (実際のブロックハッシュを使う忠実なシミュレーション)
+    #[arg(long)]
+    pub simulate_with_sigverify: bool,
+
+    /// --simulateでブロックハッシュを差し替えない
+    #[arg(long)]
+    pub keep_blockhash: bool,
+
+    /// 標準入力から "受取アドレス 金額" の行を読んで1行ずつ送金し、シグネチャを1行ずつ出力する
+    /// (金額はamountと同じ書式。不正な行や失敗した送金は報告して次へ進む)
+    #[arg(long)]
+    pub stdin: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 標準入力から読んだ秘密鍵 (key_encodingの形式) をOSのキーリングに保存する
+    StoreKey {
+        /// キーリングのサービス名 (省略時は設定ファイルのkeyring_service)
+        #[arg(long)]
+        service: Option<String>,
+        /// キーリングのユーザー名 (省略時は設定ファイルのkeyring_user)
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// 参照キーを含むトランザクションを探し、状態と受取額を表示する
+    FindPayments {
+        /// 送金時に付与した参照用の公開鍵
+        reference: String,
+        /// これより古いシグネチャを除く (スロット番号、または 2024-01-31 / 2024-01-31T12:00:00Z 形式の時刻)
+        #[arg(long, value_name = "SLOT|ISO8601", value_parser = parse_since)]
+        since: Option<Since>,
+    },
+    /// 設定・秘密鍵・RPC・残高・受取アドレス・手数料を順に確かめて結果を一覧表示する (送金はしない)
+    Doctor,
+    /// 設定ファイルのひな形を作成する (--configの最初のパス)
+    GenerateConfig,
+    /// CSV (receiver,amount[,memo]) に書かれた複数の宛先へまとめて送金する
+    Batch {
+        /// 支払いファイルのパス (amountはlamports)
+        file: String,
+        /// 確認を待たずに全トランザクションを送信し、最後にまとめて並行で確認する
+        #[arg(long)]
+        concurrent: bool,
+        /// トランザクションごとの結果 (成功/失敗/未送信・シグネチャ・理由) をJSONで書き出すパス
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+    /// 送金せず、メモ命令だけのトランザクションを送信元の署名で記録する
+    Memo {
+        /// 記録するテキスト
+        text: String,
+    },
+    /// 支払いファイルを送信せずに見積もり、送金額・手数料の合計と残高が足りるかを表示する
+    SimulateBatch {
+        /// 支払いファイルのパス (amountはlamports)
+        file: String,
+    },
+    /// 残高をポーリングして変化があるたびに表示する
+    Watch {
+        /// 監視するアドレス
+        pubkey: String,
+        /// この残高 (SOL) 以上になったら終了する
+        #[arg(long, value_parser = parse_sol_arg)]
+        until: Option<u64>,
+        /// ポーリング間隔 (秒)
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+    /// 予定した送金 (回数分) を最後まで実行し、min_balanceも残すのに必要な送信元の残高を表示する
+    RequiredFunding {
+        /// 設定ファイルの送金の代わりにこの支払いファイルのバッチを見積もる
+        #[arg(long, value_name = "FILE")]
+        batch: Option<String>,
+        /// 実行回数 (recurringの--countなど)
+        #[arg(long, default_value_t = 1)]
+        count: u64,
+    },
+    /// 合計額を複数の宛先に均等または重みに応じて分け、バッチで送金する
+    Distribute {
+        /// 配る合計額 (SOL)
+        #[arg(long, value_parser = parse_sol_arg)]
+        total: u64,
+        /// 宛先ファイル (1行に "公開鍵[,重み]"。重みを省略すると1)
+        #[arg(
+            long,
+            required_unless_present = "derive_base",
+            conflicts_with = "derive_base"
+        )]
+        receivers: Option<String>,
+        /// この公開鍵からシード 0..count (System Program) で宛先を導出して均等に配る。
+        /// 導出した宛先の資金はこの公開鍵の秘密鍵の署名とシードがないと引き出せない
+        #[arg(long, requires = "count")]
+        derive_base: Option<String>,
+        /// 導出する宛先の数
+        #[arg(long)]
+        count: Option<u32>,
+        /// 確認を待たずに全トランザクションを送信し、最後にまとめて並行で確認する
+        #[arg(long)]
+        concurrent: bool,
+        /// トランザクションごとの結果をJSONで書き出すパス
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+    /// 設定ファイルの送金を一定間隔で繰り返す
+    Recurring {
+        /// 送金の間隔 (例: 30s, 10m, 1h)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        every: Duration,
+        /// この回数送金したら終了する (省略時は止めるまで続ける)
+        #[arg(long)]
+        count: Option<u64>,
+        /// このファイルがある間は送金をスキップする (消すと再開)
+        #[arg(long, default_value = "PAUSE")]
+        pause_file: PathBuf,
+    },
+}
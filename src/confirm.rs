@@ -0,0 +1,249 @@
+use crate::{error::TransferError, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, signature::Signature, transaction::Transaction,
+};
+use std::time::{Duration, Instant};
+
+const MAX_STATUS_QUERY: usize = 256;
+const DEFAULT_FINALIZE_TIMEOUT: u64 = 120;
+
+// Polls every still-pending signature in groups of up to 256 per get_signature_statuses call.
+// Signatures that reached a final outcome are kept and not queried again.
+async fn confirm_all(
+    client: &RpcClient,
+    signatures: &[Signature],
+    timeout: u64,
+    poll_interval: Duration,
+    stale: &(dyn Fn(u64) -> bool + Sync),
+) -> Vec<Result<()>> {
+    let started = Instant::now();
+    let mut outcomes: Vec<Option<Result<()>>> = signatures.iter().map(|_| None).collect();
+
+    loop {
+        let pending: Vec<usize> = (0..signatures.len())
+            .filter(|index| outcomes[*index].is_none())
+            .collect();
+        let queries = pending.chunks(MAX_STATUS_QUERY).map(|chunk| async move {
+            let batch: Vec<Signature> = chunk.iter().map(|index| signatures[*index]).collect();
+            (chunk, client.get_signature_statuses(&batch).await)
+        });
+
+        for (chunk, response) in join_all(queries).await {
+            let statuses = match response {
+                Ok(response) if stale(response.context.slot) => continue,
+                Ok(response) => response.value,
+                Err(e) => {
+                    warn!("確認状態を取得できませんでした (次の周期で再試行): {}", e);
+                    continue;
+                }
+            };
+            for (index, status) in chunk.iter().zip(statuses) {
+                let Some(status) = status else {
+                    continue;
+                };
+                if let Some(err) = status.err {
+                    outcomes[*index] = Some(Err(anyhow!(
+                        "Transaction {} failed: {}",
+                        signatures[*index],
+                        err
+                    )));
+                } else if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                    outcomes[*index] = Some(Ok(()));
+                }
+            }
+        }
+
+        if outcomes.iter().all(Option::is_some) {
+            break;
+        }
+        if started.elapsed() >= Duration::from_secs(timeout) {
+            for (signature, outcome) in signatures.iter().zip(outcomes.iter_mut()) {
+                outcome.get_or_insert_with(|| {
+                    Err(TransferError::ConfirmationTimeout {
+                        signature: signature.to_string(),
+                        timeout,
+                    }
+                    .into())
+                });
+            }
+            break;
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+
+    outcomes.into_iter().flatten().collect()
+}
+
+impl SolanaTransactionManager {
+    // Sends the same signed transaction to every configured RPC at once. All accepted sends
+    // carry the same signature, so a single confirmation covers them.
+    pub(crate) fn broadcast_to_all(&self, transaction: &Transaction) -> Result<Signature> {
+        let urls = self.config.network.rpc_urls();
+        let config = self.send_config();
+        let clients = urls
+            .iter()
+            .map(|url| self.endpoint_client(url, CommitmentConfig::confirmed()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let sends = clients.iter().map(|client| async move {
+            client
+                .send_transaction_with_config(transaction, config)
+                .await
+        });
+        let results = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(join_all(sends))
+        });
+
+        let mut accepted = Vec::new();
+        let mut signature = None;
+        let mut last_error = None;
+        for (url, result) in urls.iter().zip(results) {
+            match result {
+                Ok(sent) => {
+                    signature.get_or_insert(sent);
+                    accepted.push(url.as_str());
+                }
+                Err(e) => {
+                    warn!("{} への送信に失敗しました: {}", url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match (signature, last_error) {
+            (Some(signature), _) => {
+                info!("送信を受け付けたRPC: {}", accepted.join(", "));
+                Ok(signature)
+            }
+            (None, Some(e)) => Err(e.into()),
+            (None, None) => Err(anyhow!("No RPC endpoint is configured")),
+        }
+    }
+
+    pub(crate) fn confirm_signatures(&self, signatures: &[Signature]) -> Vec<Result<()>> {
+        let timeout = self.config.transaction.confirmation_timeout;
+        let poll_interval = self.poll_interval();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(confirm_all(
+                &self.async_client,
+                signatures,
+                timeout,
+                poll_interval,
+                &|slot| self.stale_context(slot),
+            ))
+        })
+    }
+
+    // Keeps polling a confirmed signature until it is finalized without blocking the caller.
+    // run() waits for these tasks before the process exits.
+    pub(crate) fn track_finalization(&self, signature: Signature) {
+        let client = match self
+            .endpoint_client(&self.config.network.rpc_url, CommitmentConfig::finalized())
+        {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("ファイナライズの確認を開始できませんでした: {}", e);
+                return;
+            }
+        };
+        let timeout = Duration::from_secs(
+            self.config
+                .transaction
+                .finalize_timeout
+                .unwrap_or(DEFAULT_FINALIZE_TIMEOUT),
+        );
+        let poll_interval = self.poll_interval();
+
+        let handle = tokio::runtime::Handle::current().spawn(async move {
+            let started = Instant::now();
+            while started.elapsed() < timeout {
+                match client.get_signature_statuses(&[signature]).await {
+                    Ok(response) => {
+                        if response.value[0].as_ref().is_some_and(|status| {
+                            status.satisfies_commitment(CommitmentConfig::finalized())
+                        }) {
+                            println!("ファイナライズされました: {}", signature);
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("ファイナライズの確認に失敗しました: {}", e),
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+            warn!(
+                "{}秒以内にファイナライズされませんでした: {}",
+                timeout.as_secs(),
+                signature
+            );
+        });
+        self.background.lock().unwrap().push(handle);
+    }
+
+    pub(crate) fn wait_for_background(&self) {
+        let handles = std::mem::take(&mut *self.background.lock().unwrap());
+        if handles.is_empty() {
+            return;
+        }
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(join_all(handles))
+        });
+    }
+
+    pub(crate) fn remember_blockhash(&self, blockhash: Hash, last_valid_block_height: u64) {
+        self.blockhash_heights
+            .lock()
+            .unwrap()
+            .insert(blockhash, last_valid_block_height);
+    }
+
+    pub(crate) fn last_valid_block_height(&self, blockhash: &Hash) -> Option<u64> {
+        self.blockhash_heights
+            .lock()
+            .unwrap()
+            .get(blockhash)
+            .copied()
+    }
+
+    // Compares the current block height with the blockhash's last valid height; falls back to
+    // asking the node when the height was not recorded (e.g. an older pending state file).
+    pub(crate) fn blockhash_expired(&self, blockhash: &Hash) -> Result<bool> {
+        match self.last_valid_block_height(blockhash) {
+            Some(last_valid) => Ok(self
+                .client
+                .get_block_height_with_commitment(CommitmentConfig::confirmed())?
+                > last_valid),
+            None => Ok(!self
+                .client
+                .is_blockhash_valid(blockhash, CommitmentConfig::processed())?),
+        }
+    }
+
+    // Waits until the transaction's blockhash has expired so it can no longer land, then
+    // reports whether it landed in the meantime.
+    pub(crate) fn landed_before_expiry(&self, transaction: &Transaction) -> Result<bool> {
+        let signature = transaction.signatures[0];
+        loop {
+            let expired = self.blockhash_expired(&transaction.message.recent_blockhash)?;
+            let response = self
+                .client
+                .get_signature_statuses_with_history(&[signature])?;
+            if self.stale_context(response.context.slot) {
+                std::thread::sleep(self.poll_interval());
+                continue;
+            }
+            if let Some(status) = response.value[0].clone() {
+                if let Some(err) = status.err {
+                    return Err(anyhow!("Transaction {} failed: {}", signature, err));
+                }
+                return Ok(true);
+            }
+            if expired {
+                return Ok(false);
+            }
+            std::thread::sleep(self.poll_interval());
+        }
+    }
+}
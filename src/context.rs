@@ -0,0 +1,198 @@
+use crate::SolanaTransactionManager;
+use anyhow::Result;
+use log::info;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_config::RpcContextConfig,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_response::Response,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+fn rank(commitment: CommitmentConfig) -> usize {
+    if commitment.is_finalized() {
+        2
+    } else if commitment.is_confirmed() {
+        1
+    } else {
+        0
+    }
+}
+
+// Highest context slot seen per commitment (processed, confirmed, finalized). A read may only
+// require a slot seen at its own or a stronger commitment: a processed slot is ahead of the
+// confirmed bank, and using it as minContextSlot for a confirmed read would never be satisfied.
+#[derive(Debug, Default)]
+pub(crate) struct SlotTracker {
+    slots: [AtomicU64; 3],
+}
+
+impl SlotTracker {
+    pub(crate) fn observe(&self, commitment: CommitmentConfig, slot: u64) {
+        self.slots[rank(commitment)].fetch_max(slot, Ordering::Relaxed);
+    }
+
+    pub(crate) fn min_context_slot(&self, commitment: CommitmentConfig) -> Option<u64> {
+        self.slots[rank(commitment)..]
+            .iter()
+            .map(|slot| slot.load(Ordering::Relaxed))
+            .max()
+            .filter(|slot| *slot > 0)
+    }
+
+    // Records the response and reports whether it came from a bank behind what was already seen.
+    pub(crate) fn stale(&self, commitment: CommitmentConfig, context_slot: u64) -> Option<u64> {
+        let latest = self.min_context_slot(commitment);
+        self.observe(commitment, context_slot);
+        latest.filter(|latest| context_slot < *latest)
+    }
+}
+
+fn min_context_slot_not_reached(error: &ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+    )
+}
+
+impl SolanaTransactionManager {
+    // The highest slot seen at the read's commitment or stronger, seeded from get_slot at that
+    // commitment on first use.
+    fn read_min_context_slot(&self, commitment: CommitmentConfig) -> Result<Option<u64>> {
+        if !self.config.network.read_min_context_slot {
+            return Ok(None);
+        }
+        if self.slots.min_context_slot(commitment).is_none() {
+            self.slots.observe(
+                commitment,
+                self.client.get_slot_with_commitment(commitment)?,
+            );
+        }
+        Ok(self.slots.min_context_slot(commitment))
+    }
+
+    pub(crate) fn balance_with_context(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<u64> {
+        let Some(min_context_slot) = self.read_min_context_slot(commitment)? else {
+            return Ok(self
+                .client
+                .get_balance_with_commitment(pubkey, commitment)?
+                .value);
+        };
+
+        self.balance_at_slot(pubkey, commitment, min_context_slot)
+    }
+
+    fn balance_at_slot(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+        min_context_slot: u64,
+    ) -> Result<u64> {
+        let config = RpcContextConfig {
+            commitment: Some(commitment),
+            min_context_slot: Some(min_context_slot),
+        };
+        let response: Response<u64> = self.client.send(
+            RpcRequest::GetBalance,
+            serde_json::json!([pubkey.to_string(), config]),
+        )?;
+        self.slots.observe(commitment, response.context.slot);
+        Ok(response.value)
+    }
+
+    // The balance read right after a transaction landed in `slot`: a bank before that slot
+    // would not include it yet, so the node is waited for until it has caught up.
+    pub(crate) fn balance_since_slot(&self, pubkey: &Pubkey, slot: u64) -> Result<u64> {
+        let commitment = self.balance_commitment();
+        let timeout = Duration::from_secs(self.config.transaction.confirmation_timeout);
+        let started = Instant::now();
+        loop {
+            match self.balance_at_slot(pubkey, commitment, slot) {
+                Err(e)
+                    if e.downcast_ref().is_some_and(min_context_slot_not_reached)
+                        && started.elapsed() < timeout =>
+                {
+                    std::thread::sleep(self.poll_interval());
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // getSignatureStatuses takes no minContextSlot and answers from the processed bank, so a
+    // response behind the latest processed (or stronger) slot seen is reported as stale and the
+    // caller treats it as "not seen yet".
+    pub(crate) fn stale_context(&self, context_slot: u64) -> bool {
+        if !self.config.network.read_min_context_slot {
+            return false;
+        }
+        match self
+            .slots
+            .stale(CommitmentConfig::processed(), context_slot)
+        {
+            Some(latest) => {
+                info!(
+                    "RPCの応答がスロット {} 時点で、既知のスロット {} より古いため無視します",
+                    context_slot, latest
+                );
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processed_slots_are_not_required_of_confirmed_reads() {
+        let slots = SlotTracker::default();
+        slots.observe(CommitmentConfig::confirmed(), 100);
+        slots.observe(CommitmentConfig::processed(), 130);
+
+        assert_eq!(
+            slots.min_context_slot(CommitmentConfig::confirmed()),
+            Some(100)
+        );
+        assert_eq!(
+            slots.min_context_slot(CommitmentConfig::processed()),
+            Some(130)
+        );
+        assert_eq!(slots.min_context_slot(CommitmentConfig::finalized()), None);
+    }
+
+    #[test]
+    fn stronger_commitment_slots_apply_to_weaker_reads() {
+        let slots = SlotTracker::default();
+        slots.observe(CommitmentConfig::finalized(), 90);
+
+        assert_eq!(
+            slots.min_context_slot(CommitmentConfig::confirmed()),
+            Some(90)
+        );
+        slots.observe(CommitmentConfig::confirmed(), 80);
+        assert_eq!(
+            slots.min_context_slot(CommitmentConfig::confirmed()),
+            Some(90)
+        );
+    }
+
+    #[test]
+    fn stale_compares_against_the_same_commitment() {
+        let slots = SlotTracker::default();
+        assert_eq!(slots.stale(CommitmentConfig::processed(), 120), None);
+        assert_eq!(slots.stale(CommitmentConfig::processed(), 110), Some(120));
+        assert_eq!(slots.stale(CommitmentConfig::processed(), 125), None);
+        assert_eq!(slots.min_context_slot(CommitmentConfig::confirmed()), None);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    batch::{BatchRecipient, BatchStatus},
+    error::TransferError,
+    format::format_sol,
+    SendOptions, SolanaTransactionManager,
+};
+use anyhow::{anyhow, Result};
+use solana_program::system_program;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::str::FromStr;
+
+pub enum Receivers {
+    File(String),
+    Derived { base: Pubkey, count: u32 },
+}
+
+// One "pubkey[,weight]" per line; the weight defaults to 1.
+fn load_weighted(path: &str) -> Result<Vec<(Pubkey, u64)>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| TransferError::Config(format!("Failed to read {}: {}", path, e)))?;
+
+    let mut receivers = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (receiver, weight) = line.split_once(',').unwrap_or((line, "1"));
+        let receiver = Pubkey::from_str(receiver.trim()).map_err(|e| {
+            TransferError::Config(format!("{}:{}: invalid receiver: {}", path, index + 1, e))
+        })?;
+        let weight = weight
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|weight| *weight > 0)
+            .ok_or_else(|| {
+                TransferError::Config(format!(
+                    "{}:{}: weight must be a positive integer",
+                    path,
+                    index + 1
+                ))
+            })?;
+        receivers.push((receiver, weight));
+    }
+
+    Ok(receivers)
+}
+
+fn derive_receivers(base: &Pubkey, count: u32) -> Result<Vec<(Pubkey, u64)>> {
+    (0..count)
+        .map(|index| {
+            Pubkey::create_with_seed(base, &index.to_string(), &system_program::id())
+                .map(|receiver| (receiver, 1))
+                .map_err(|e| anyhow!("Failed to derive receiver #{}: {}", index, e))
+        })
+        .collect()
+}
+
+// Splits total proportionally to the weights; the lamports lost to rounding go one each to
+// the first receivers so the amounts always add up to total.
+fn split(total: u64, weighted: &[(Pubkey, u64)]) -> Result<Vec<BatchRecipient>> {
+    let weights: u128 = weighted.iter().map(|(_, weight)| *weight as u128).sum();
+    if weights == 0 {
+        return Err(TransferError::Config("Distribution has no receivers".to_string()).into());
+    }
+
+    let mut recipients: Vec<BatchRecipient> = weighted
+        .iter()
+        .map(|(receiver, weight)| BatchRecipient {
+            receiver: *receiver,
+            amount: (total as u128 * *weight as u128 / weights) as u64,
+            memo: None,
+        })
+        .collect();
+    let distributed: u64 = recipients.iter().map(|recipient| recipient.amount).sum();
+    for recipient in recipients.iter_mut().take((total - distributed) as usize) {
+        recipient.amount += 1;
+    }
+
+    if let Some(recipient) = recipients.iter().find(|recipient| recipient.amount == 0) {
+        return Err(TransferError::Config(format!(
+            "Total {} is too small to give {} a non-zero amount",
+            format_sol(total),
+            recipient.receiver
+        ))
+        .into());
+    }
+
+    Ok(recipients)
+}
+
+impl SolanaTransactionManager {
+    pub fn distribute(
+        &self,
+        total: u64,
+        receivers: Receivers,
+        options: &SendOptions,
+        concurrent: bool,
+        report: Option<&Path>,
+    ) -> Result<Vec<String>> {
+        let (source, weighted) = match receivers {
+            Receivers::File(path) => {
+                let weighted = load_weighted(&path)?;
+                (path, weighted)
+            }
+            Receivers::Derived { base, count } => {
+                let weighted = derive_receivers(&base, count)?;
+                println!("導出した宛先 (引き出しにはbase鍵の署名とシードが必要):");
+                for (seed, (receiver, _)) in weighted.iter().enumerate() {
+                    println!("  {} base {} seed \"{}\"", receiver, base, seed);
+                }
+                (format!("{} (0..{})", base, count), weighted)
+            }
+        };
+        let recipients = self.handle_duplicate_recipients(&source, split(total, &weighted)?)?;
+
+        let outcomes = if concurrent {
+            self.send_recipients_concurrent(&recipients, options)?
+        } else {
+            self.send_recipients(&recipients, options)?
+        };
+
+        if self.config.output.table {
+            return self.finish_batch(outcomes, report);
+        }
+
+        println!("配布結果:");
+        for outcome in &outcomes {
+            let result = match outcome.status {
+                BatchStatus::Confirmed => outcome.signature.clone().unwrap_or_default(),
+                BatchStatus::Failed => "失敗".to_string(),
+                BatchStatus::Skipped => "未送信".to_string(),
+            };
+            for recipient in &outcome.recipients {
+                println!(
+                    "  {} {} {}",
+                    recipient.receiver,
+                    format_sol(recipient.amount),
+                    result
+                );
+            }
+        }
+
+        self.finish_batch(outcomes, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receiver(seed: u8) -> Pubkey {
+        Pubkey::new_from_array([seed; 32])
+    }
+
+    #[test]
+    fn split_follows_weights_and_hands_out_the_rounding_remainder() {
+        let recipients =
+            split(10, &[(receiver(1), 1), (receiver(2), 1), (receiver(3), 1)]).unwrap();
+        let amounts: Vec<u64> = recipients
+            .iter()
+            .map(|recipient| recipient.amount)
+            .collect();
+        assert_eq!(amounts, vec![4, 3, 3]);
+
+        let recipients = split(100, &[(receiver(1), 3), (receiver(2), 1)]).unwrap();
+        let amounts: Vec<u64> = recipients
+            .iter()
+            .map(|recipient| recipient.amount)
+            .collect();
+        assert_eq!(amounts, vec![75, 25]);
+    }
+
+    #[test]
+    fn split_rejects_a_total_too_small_for_every_receiver() {
+        assert!(split(1, &[(receiver(1), 1), (receiver(2), 1)]).is_err());
+        assert!(split(1, &[]).is_err());
+    }
+
+    #[test]
+    fn derived_receivers_are_stable() {
+        let base = receiver(1);
+        let derived = derive_receivers(&base, 3).unwrap();
+        assert_eq!(derived.len(), 3);
+        assert_eq!(
+            derived[2].0,
+            Pubkey::create_with_seed(&base, "2", &system_program::id()).unwrap()
+        );
+        assert!(derived.iter().all(|(_, weight)| *weight == 1));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{format::format_sol, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Ok,
+    Warn,
+    Fail,
+}
+
+fn report(check: Check, name: &str, detail: impl std::fmt::Display) {
+    let (mark, color) = match check {
+        Check::Ok => ("OK", "32"),
+        Check::Warn => ("注意", "33"),
+        Check::Fail => ("NG", "31"),
+    };
+    if std::io::stdout().is_terminal() {
+        println!("\x1b[{}m[{}]\x1b[0m {}: {}", color, mark, name, detail);
+    } else {
+        println!("[{}] {}: {}", mark, name, detail);
+    }
+}
+
+pub fn report_config_failure(error: &anyhow::Error) {
+    report(Check::Fail, "設定ファイル", error);
+}
+
+impl SolanaTransactionManager {
+    // Runs every setup check it can without sending anything; later checks are skipped when
+    // the ones they depend on failed.
+    pub fn doctor(&self, config_paths: &[String]) -> Result<()> {
+        let mut failed = 0;
+        let mut fail = |name: &str, detail: String| {
+            report(Check::Fail, name, detail);
+            failed += 1;
+        };
+
+        report(Check::Ok, "設定ファイル", config_paths.join(", "));
+
+        let sender = match self.create_sender_keypair() {
+            Ok(keypair) => {
+                report(Check::Ok, "秘密鍵", keypair.pubkey());
+                Some(keypair.pubkey())
+            }
+            Err(e) => {
+                fail("秘密鍵", e.to_string());
+                None
+            }
+        };
+
+        let rpc_ok = match self.client.get_health() {
+            Ok(()) => {
+                report(Check::Ok, "RPC", &self.config.network.rpc_url);
+                true
+            }
+            Err(e) => {
+                fail("RPC", format!("{} ({})", self.config.network.rpc_url, e));
+                false
+            }
+        };
+
+        let balance = match (sender, rpc_ok) {
+            (Some(sender), true) => match self.get_balance(&sender) {
+                Ok(balance) => {
+                    report(Check::Ok, "送信元の残高", format_sol(balance));
+                    Some(balance)
+                }
+                Err(e) => {
+                    fail("送信元の残高", e.to_string());
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        match self.receiver_pubkey() {
+            Ok(receiver) if rpc_ok => match self.client.get_account(&receiver) {
+                Ok(_) => report(Check::Ok, "受取アドレス", receiver),
+                Err(_) => report(
+                    Check::Warn,
+                    "受取アドレス",
+                    format!(
+                        "{} (まだアカウントがありません。送金で作成されます)",
+                        receiver
+                    ),
+                ),
+            },
+            Ok(receiver) => report(Check::Ok, "受取アドレス", receiver),
+            Err(e) => fail("受取アドレス", e.to_string()),
+        }
+
+        if let (Some(sender), Some(balance)) = (sender, balance) {
+            let estimate = self.amount().and_then(|amount| {
+                let fee = self.estimate_transfer_fee(&sender, amount)?;
+                let lamports = if self.config.token.is_some() {
+                    0
+                } else {
+                    amount
+                };
+                Ok((fee, lamports + fee + self.min_balance(balance)))
+            });
+            match estimate {
+                Ok((fee, required)) if balance >= required => report(
+                    Check::Ok,
+                    "手数料と残高",
+                    format!(
+                        "手数料 {} / 必要額 {} / 残高 {}",
+                        format_sol(fee),
+                        format_sol(required),
+                        format_sol(balance)
+                    ),
+                ),
+                Ok((fee, required)) => fail(
+                    "手数料と残高",
+                    format!(
+                        "手数料 {} / 必要額 {} / 残高 {} ({} 不足)",
+                        format_sol(fee),
+                        format_sol(required),
+                        format_sol(balance),
+                        format_sol(required - balance)
+                    ),
+                ),
+                Err(e) => fail("手数料と残高", e.to_string()),
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow!("{} setup check(s) failed", failed));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    error::TransferError, format::format_sol, SendOptions, SolanaTransactionManager, TransferCost,
+};
+use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct Issue {
+    severity: Severity,
+    check: &'static str,
+    message: String,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, check: &'static str, message: impl Display) {
+        self.issues.push(Issue {
+            severity,
+            check,
+            message: message.to_string(),
+        });
+    }
+
+    fn error(&mut self, check: &'static str, message: impl Display) {
+        self.push(Severity::Error, check, message);
+    }
+
+    fn warning(&mut self, check: &'static str, message: impl Display) {
+        self.push(Severity::Warning, check, message);
+    }
+
+    fn check<T>(&mut self, check: &'static str, result: Result<T>) -> Option<T> {
+        result.map_err(|e| self.error(check, e)).ok()
+    }
+
+    fn errors(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count()
+    }
+}
+
+impl SolanaTransactionManager {
+    // Runs the send path's preflight checks and collects every problem instead of stopping at
+    // the first one. A check is skipped only when something it needs could not be determined.
+    fn dry_run_report(&self, options: &SendOptions) -> Report {
+        let mut report = Report::default();
+
+        let sender = report.check(
+            "sender",
+            self.create_sender_keypair().map(|keypair| keypair.pubkey()),
+        );
+        let receiver = report.check("receiver", self.receiver_pubkey());
+        if let Some(receiver) = receiver {
+            report.check("receiver", self.check_receiver_allowed(&receiver));
+        }
+        let mint = report.check("token", self.token_mint());
+        let amount = report.check("amount", self.amount());
+        if amount == Some(0) && !options.allow_zero {
+            report.error(
+                "amount",
+                "amount is 0; pass --allow-zero to send a zero-amount transaction anyway",
+            );
+        }
+        let balance = sender.and_then(|sender| report.check("balance", self.get_balance(&sender)));
+
+        let mint = mint.flatten();
+        let lamports = match mint {
+            Some(_) => 0,
+            None => amount.unwrap_or(0),
+        };
+        let mut account_rent = 0;
+        if let (Some(mint), Some(receiver)) = (mint, receiver) {
+            report.check("receiver", self.check_receiver_curve(&receiver));
+            if self.receiver_is_pda() {
+                report.check(
+                    "receiver_token_account",
+                    self.load_token_account(&receiver, &mint, "Receiver"),
+                );
+            } else if let Some(rent) = report.check(
+                "receiver_token_account",
+                self.token_account_rent(&receiver, &mint),
+            ) {
+                account_rent = rent;
+                if rent > 0 {
+                    report.warning(
+                        "receiver_token_account",
+                        format!(
+                            "Receiver {} has no associated token account yet; the transfer creates it for {}",
+                            receiver,
+                            format_sol(rent)
+                        ),
+                    );
+                }
+            }
+        }
+        if let (Some(mint), Some(sender), Some(amount)) = (mint, sender, amount) {
+            if let Some(source) =
+                report.check("token_balance", self.sender_token_account(&sender, &mint))
+            {
+                report.check(
+                    "token_balance",
+                    self.check_sender_token_account(&source, &mint, amount),
+                );
+            }
+            report.check("amount", self.check_token_amount(&mint, amount));
+        }
+
+        if let (Some(sender), Some(amount), Some(balance)) = (sender, amount, balance) {
+            let message = report.check("fee", self.transfer_message(&sender, amount));
+            if let Some(message) = &message {
+                self.trace_message(message);
+            }
+            let fee = message.and_then(|message| {
+                report.check(
+                    "fee",
+                    self.client
+                        .get_fee_for_message(&message)
+                        .map_err(anyhow::Error::from),
+                )
+            });
+            if let Some(fee) = fee {
+                let required = lamports + fee + self.min_balance(balance);
+                if balance < required {
+                    report.error(
+                        "balance",
+                        TransferError::InsufficientBalance { balance, required },
+                    );
+                }
+                if mint.is_none() {
+                    report.check("amount", self.check_dust(lamports, fee, options));
+                }
+                report.check(
+                    "fee",
+                    self.check_total_cost(&TransferCost {
+                        amount: lamports,
+                        fee,
+                        account_rent,
+                    }),
+                );
+            }
+        }
+
+        if let Some(threshold) = self.config.transaction.large_transfer_threshold {
+            if mint.is_none() && lamports > threshold && !options.yes_large {
+                report.warning(
+                    "amount",
+                    format!(
+                        "Transfer amount {} exceeds large_transfer_threshold {}; the amount has to be typed to confirm",
+                        format_sol(lamports),
+                        format_sol(threshold)
+                    ),
+                );
+            }
+        }
+
+        report
+    }
+
+    pub fn dry_run(&self, options: &SendOptions, report_path: Option<&Path>) -> Result<()> {
+        let report = self.dry_run_report(options);
+
+        if report.issues.is_empty() {
+            println!("ドライラン: 問題は見つかりませんでした (送金はしていません)");
+        } else {
+            println!(
+                "ドライラン: {}件の問題が見つかりました (送金はしていません)",
+                report.issues.len()
+            );
+            for (index, issue) in report.issues.iter().enumerate() {
+                let severity = match issue.severity {
+                    Severity::Error => "エラー",
+                    Severity::Warning => "注意",
+                };
+                println!(
+                    "  {}. [{}] {}: {}",
+                    index + 1,
+                    severity,
+                    issue.check,
+                    issue.message
+                );
+            }
+        }
+
+        if let Some(path) = report_path {
+            std::fs::write(path, serde_json::to_string_pretty(&report.issues)?)?;
+            println!("ドライランの結果を書き出しました: {}", path.display());
+        }
+
+        match report.errors() {
+            0 => Ok(()),
+            errors => Err(anyhow!("Dry run found {} problem(s)", errors)),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::format::format_sol;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TransferError {
+    #[error(
+        "Insufficient balance. Current balance: {}, Required: {}. Top up at least {} to send",
+        format_sol(*.balance),
+        format_sol(*.required),
+        format_sol(.required.saturating_sub(*.balance))
+    )]
+    InsufficientBalance { balance: u64, required: u64 },
+    #[error("Insufficient token balance. Current balance: {balance}, Required: {required}")]
+    InsufficientTokenBalance { balance: u64, required: u64 },
+    #[error("Transaction {signature} was not confirmed within {timeout} seconds")]
+    ConfirmationTimeout { signature: String, timeout: u64 },
+    #[error(
+        "Transaction {signature} was not confirmed and its blockhash expired; it can no longer land and is safe to retry"
+    )]
+    BlockhashExpired { signature: String },
+    #[error("Config error: {0}")]
+    Config(String),
+    #[error("Receiver {receiver} is on the blocked receivers list")]
+    BlockedReceiver { receiver: String },
+    #[error("{failed} of {total} batch transactions did not succeed")]
+    PartialBatchFailure { failed: usize, total: usize },
+}
+
+impl TransferError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TransferError::InsufficientBalance { .. }
+            | TransferError::InsufficientTokenBalance { .. } => 2,
+            TransferError::ConfirmationTimeout { .. } | TransferError::BlockhashExpired { .. } => 3,
+            TransferError::Config(_) => 4,
+            TransferError::BlockedReceiver { .. } => 5,
+            TransferError::PartialBatchFailure { .. } => 6,
+        }
+    }
+}
+
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<TransferError>()
+        .map(TransferError::exit_code)
+        .unwrap_or(1)
+}
+
+pub fn is_confirmation_timeout(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<TransferError>(),
+        Some(TransferError::ConfirmationTimeout { .. })
+    )
+}
+
+pub fn is_blockhash_expired(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<TransferError>(),
+        Some(TransferError::BlockhashExpired { .. })
+    )
+}
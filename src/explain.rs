@@ -0,0 +1,81 @@
+use crate::{format::format_sol, SolanaTransactionManager};
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct Preview {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub balance: u64,
+    pub amount: u64,
+    pub mint: Option<Pubkey>,
+    pub fee: u64,
+    pub account_rent: u64,
+    pub min_balance: u64,
+    pub receiver_exists: bool,
+}
+
+impl Preview {
+    pub fn steps(&self) -> Vec<String> {
+        let lamports = if self.mint.is_some() { 0 } else { self.amount };
+        let required = lamports + self.fee + self.account_rent + self.min_balance;
+
+        let mut steps = vec![format!(
+            "送信元 {} の残高は {}",
+            self.sender,
+            format_sol(self.balance)
+        )];
+        match self.mint {
+            Some(mint) => steps.push(format!(
+                "トークン {} を {} (最小単位) 送る。SOLは手数料とアカウント作成分だけ使う",
+                mint, self.amount
+            )),
+            None => steps.push(format!(
+                "{} を送るには手数料 {} を含めて {} が必要",
+                format_sol(self.amount),
+                format_sol(self.fee),
+                format_sol(lamports + self.fee)
+            )),
+        }
+        if self.account_rent > 0 {
+            steps.push(format!(
+                "受取側のトークンアカウントが無いので作成分のレント {} もかかる",
+                format_sol(self.account_rent)
+            ));
+        }
+        steps.push(format!(
+            "min_balance {} を残すので、必要な残高は合計 {} ({})",
+            format_sol(self.min_balance),
+            format_sol(required),
+            if self.balance >= required {
+                "足りている"
+            } else {
+                "足りない"
+            }
+        ));
+        steps.push(if self.receiver_exists {
+            format!("受取アドレス {} は既に存在する", self.receiver)
+        } else {
+            format!(
+                "受取アドレス {} はまだ存在しない (この送金で作成される)",
+                self.receiver
+            )
+        });
+
+        steps
+    }
+}
+
+impl SolanaTransactionManager {
+    pub(crate) fn explain(&self, message: impl Display) {
+        if self.config.output.explain {
+            println!("[explain] {}", message);
+        }
+    }
+
+    pub(crate) fn explain_preview(&self, preview: &Preview) {
+        for step in preview.steps() {
+            self.explain(step);
+        }
+    }
+}
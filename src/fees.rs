@@ -0,0 +1,276 @@
+use crate::{
+    error::{is_blockhash_expired, TransferError},
+    SolanaTransactionManager, TransferCost,
+};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use std::time::{Duration, Instant};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const DEFAULT_FEE_WAIT_TIMEOUT: u64 = 300;
+const DEFAULT_FEE_WAIT_INTERVAL: u64 = 10;
+const DEFAULT_ESCALATION_ATTEMPTS: u32 = 3;
+
+fn compute_unit_price(instructions: &[Instruction]) -> u64 {
+    let discriminant = ComputeBudgetInstruction::set_compute_unit_price(0).data[0];
+    instructions
+        .iter()
+        .filter(|instruction| instruction.program_id == compute_budget::id())
+        .find_map(|instruction| match instruction.data.split_first() {
+            Some((first, price)) if *first == discriminant => {
+                Some(u64::from_le_bytes(price.try_into().ok()?))
+            }
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+fn with_compute_unit_price(instructions: &[Instruction], price: u64) -> Vec<Instruction> {
+    let price_instruction = ComputeBudgetInstruction::set_compute_unit_price(price);
+    let mut escalated: Vec<Instruction> = instructions
+        .iter()
+        .filter(|instruction| {
+            instruction.program_id != compute_budget::id()
+                || instruction.data.first() != price_instruction.data.first()
+        })
+        .cloned()
+        .collect();
+    escalated.insert(0, price_instruction);
+    escalated
+}
+
+fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    accounts.sort_unstable();
+    accounts.dedup();
+    accounts
+}
+
+fn is_unsupported(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+            let message = message.to_lowercase();
+            *code == METHOD_NOT_FOUND
+                || message.contains("method not found")
+                || message.contains("not supported")
+        }
+        _ => false,
+    }
+}
+
+impl SolanaTransactionManager {
+    fn recent_fee_median(&self, accounts: &[Pubkey]) -> Result<u64> {
+        let mut fees: Vec<u64> = self
+            .client
+            .get_recent_prioritization_fees(accounts)?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        fees.sort_unstable();
+        Ok(fees.get(fees.len() / 2).copied().unwrap_or(0))
+    }
+
+    pub(crate) fn wait_for_fee_market(&self, accounts: &[Pubkey]) -> Result<()> {
+        let transaction = &self.config.transaction;
+        let Some(threshold) = transaction.fee_wait_threshold else {
+            return Ok(());
+        };
+        let timeout = transaction
+            .fee_wait_timeout
+            .unwrap_or(DEFAULT_FEE_WAIT_TIMEOUT);
+        let interval = transaction
+            .fee_wait_interval
+            .unwrap_or(DEFAULT_FEE_WAIT_INTERVAL);
+        let started = Instant::now();
+
+        loop {
+            let median = self.recent_fee_median(accounts)?;
+            info!(
+                "直近の優先手数料の中央値: {} micro-lamports/CU (しきい値 {})",
+                median, threshold
+            );
+            if median <= threshold {
+                return Ok(());
+            }
+            if started.elapsed() >= Duration::from_secs(timeout) {
+                return Err(anyhow!(
+                    "Priority fees stayed above {} micro-lamports/CU for {} seconds",
+                    threshold,
+                    timeout
+                ));
+            }
+            std::thread::sleep(Duration::from_secs(interval));
+        }
+    }
+
+    // micro-lamports per compute unit; None leaves the priority fee off entirely
+    pub(crate) fn priority_fee(&self, accounts: &[Pubkey]) -> Result<Option<u64>> {
+        let transaction = &self.config.transaction;
+        if transaction.adaptive_priority_fee {
+            return Ok(None);
+        }
+        if !transaction.auto_priority_fee {
+            return Ok(transaction.compute_unit_price);
+        }
+        self.estimated_priority_fee(accounts).map(Some)
+    }
+
+    fn estimated_priority_fee(&self, accounts: &[Pubkey]) -> Result<u64> {
+        let transaction = &self.config.transaction;
+        let floor = transaction.priority_fee_floor.unwrap_or(0);
+        let median = match self.recent_fee_median(accounts) {
+            Ok(median) => median,
+            Err(e) if e.downcast_ref().is_some_and(is_unsupported) => {
+                warn!(
+                    "RPCがget_recent_prioritization_feesに対応していないため下限の優先手数料 {} を使います: {}",
+                    floor, e
+                );
+                return Ok(floor);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let price = median.max(floor);
+        info!("優先手数料: {} micro-lamports/CU", price);
+
+        Ok(price)
+    }
+}
+
+impl SolanaTransactionManager {
+    // A higher priority fee must still fit max_total_cost and the sender's balance above the
+    // reserve, the same limits the first attempt was checked against. Both include the rent of
+    // a receiver token account the transfer creates.
+    fn check_escalated_cost(&self, sender: &Pubkey, cost: TransferCost) -> Result<()> {
+        self.check_total_cost(&cost)?;
+        self.check_cost_covered(self.get_balance(sender)?, &cost)
+    }
+
+    fn check_cost_covered(&self, balance: u64, cost: &TransferCost) -> Result<()> {
+        let required = cost.total() + self.min_balance(balance);
+        if balance < required {
+            return Err(TransferError::InsufficientBalance { balance, required }.into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn submit_with_fee_escalation(
+        &self,
+        transaction: Transaction,
+        instructions: &[Instruction],
+        signer: &dyn Signer,
+        amount: u64,
+        cost: TransferCost,
+        deadline: Option<u64>,
+    ) -> Result<(Signature, u64)> {
+        let fee = cost.fee;
+        let config = &self.config.transaction;
+        let mut price = compute_unit_price(instructions);
+        // adaptive_priority_fee: the first attempt goes out without a priority fee and only a
+        // timeout that did not land adds the estimated fee, before any escalation steps.
+        let adaptive = config.adaptive_priority_fee && price == 0;
+        let escalation_attempts = match config.fee_escalation_step {
+            Some(_) => config
+                .fee_escalation_attempts
+                .unwrap_or(DEFAULT_ESCALATION_ATTEMPTS),
+            None => 1,
+        };
+        let attempts = escalation_attempts + u32::from(adaptive);
+        if attempts <= 1 {
+            return Ok((
+                self.submit_transaction(&transaction, amount, deadline)?,
+                fee,
+            ));
+        }
+        let step = config.fee_escalation_step.unwrap_or(0);
+        let cap = config.fee_escalation_cap.unwrap_or(u64::MAX);
+
+        let mut transaction = transaction;
+        let mut fee = fee;
+        let mut attempt = 1;
+        loop {
+            match self.submit_transaction(&transaction, amount, deadline) {
+                Err(e) if is_blockhash_expired(&e) && attempt < attempts => {
+                    if adaptive && attempt == 1 {
+                        price = self.estimated_priority_fee(&writable_accounts(instructions))?;
+                    } else {
+                        price = price.saturating_add(step).min(cap);
+                    }
+                    attempt += 1;
+                    warn!(
+                        "着地しなかったため優先手数料 {} micro-lamports/CU で再送します ({}/{})",
+                        price, attempt, attempts
+                    );
+                    transaction = self
+                        .sign_transaction(&with_compute_unit_price(instructions, price), signer)?;
+                    fee = self.client.get_fee_for_message(&transaction.message)?;
+                    self.check_escalated_cost(&signer.pubkey(), TransferCost { fee, ..cost })?;
+                }
+                result => return result.map(|signature| (signature, fee)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::manager;
+
+    #[test]
+    fn escalated_cost_counts_account_rent() {
+        let manager = manager("max_total_cost = 10000", "");
+        let cost = TransferCost {
+            amount: 0,
+            fee: 5_000,
+            account_rent: 2_039_280,
+        };
+        assert!(manager.check_total_cost(&cost).is_err());
+        assert!(manager.check_cost_covered(5_000, &cost).is_err());
+        assert!(manager.check_cost_covered(2_044_280, &cost).is_ok());
+
+        let cost = TransferCost {
+            account_rent: 0,
+            ..cost
+        };
+        assert!(manager.check_total_cost(&cost).is_ok());
+        assert!(manager.check_cost_covered(5_000, &cost).is_ok());
+    }
+
+    #[test]
+    fn escalation_replaces_the_compute_unit_price() {
+        let transfer = solana_program::system_instruction::transfer(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1,
+        );
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(100),
+            transfer.clone(),
+        ];
+        assert_eq!(compute_unit_price(&instructions), 100);
+        assert_eq!(compute_unit_price(&instructions[2..]), 0);
+
+        let escalated = with_compute_unit_price(&instructions, 250);
+        assert_eq!(escalated.len(), 3);
+        assert_eq!(compute_unit_price(&escalated), 250);
+        assert_eq!(escalated.last(), Some(&transfer));
+    }
+}
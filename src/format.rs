@@ -0,0 +1,165 @@
+use crate::amount::sol_decimal;
+use clap::ValueEnum;
+use std::sync::OnceLock;
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+static UNITS: OnceLock<Units> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Units {
+    #[default]
+    Sol,
+    Lamports,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Locale {
+    /// 1234567.89
+    #[default]
+    Plain,
+    /// 1,234,567.89
+    En,
+    /// 1.234.567,89
+    De,
+    /// 1 234 567,89
+    Fr,
+}
+
+impl Locale {
+    fn separators(self) -> (Option<char>, char) {
+        match self {
+            Locale::Plain => (None, '.'),
+            Locale::En => (Some(','), '.'),
+            Locale::De => (Some('.'), ','),
+            Locale::Fr => (Some(' '), ','),
+        }
+    }
+}
+
+pub fn set_locale(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+pub fn set_units(units: Units) {
+    let _ = UNITS.set(units);
+}
+
+fn localize(number: &str) -> String {
+    let (grouping, decimal) = LOCALE.get().copied().unwrap_or_default().separators();
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+
+    let mut output = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if let Some(separator) = grouping {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                output.push(separator);
+            }
+        }
+        output.push(digit);
+    }
+
+    if let Some(fraction) = fraction {
+        output.push(decimal);
+        output.push_str(fraction);
+    }
+
+    output
+}
+
+pub fn sol_amount(lamports: u64) -> String {
+    localize(&sol_decimal(lamports))
+}
+
+pub fn units() -> Units {
+    UNITS.get().copied().unwrap_or_default()
+}
+
+pub fn format_amount(lamports: u64, units: Units) -> String {
+    match units {
+        Units::Sol => format!("{} SOL", sol_amount(lamports)),
+        Units::Lamports => format!("{} lamports", localize(&lamports.to_string())),
+    }
+}
+
+pub fn format_sol(lamports: u64) -> String {
+    format_amount(lamports, units())
+}
+
+pub fn render_template(template: &str, fields: &[(&str, String)]) -> String {
+    fields
+        .iter()
+        .fold(template.to_string(), |output, (name, value)| {
+            output.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+// Terminal columns taken by the text; CJK characters are two columns wide.
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+pub fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| display_width(header)).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - display_width(cell))))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let separator = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>()
+        .join("-+-");
+
+    let mut output = vec![line(headers.to_vec()), separator];
+    output.extend(
+        rows.iter()
+            .map(|row| line(row.iter().map(String::as_str).collect())),
+    );
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_pads_wide_characters_by_display_width() {
+        let table = render_table(
+            &["受取", "amount"],
+            &[
+                vec!["abc".to_string(), "1 SOL".to_string()],
+                vec!["a".to_string(), "".to_string()],
+            ],
+        );
+        assert_eq!(table, "受取 | amount\n-----+-------\nabc  | 1 SOL\na    |");
+    }
+
+    #[test]
+    fn template_replaces_named_fields() {
+        assert_eq!(
+            render_template(
+                "{signature} -> {receiver}",
+                &[
+                    ("signature", "sig".to_string()),
+                    ("receiver", "r".to_string())
+                ]
+            ),
+            "sig -> r"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    batch::{batch_total, pack_transactions},
+    format::format_sol,
+    SolanaTransactionManager,
+};
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+
+struct FundingPlan {
+    runs: u64,
+    amount: u64,
+    fee: u64,
+    account_rent: u64,
+}
+
+impl FundingPlan {
+    fn spend(&self) -> u64 {
+        (self.amount + self.fee) * self.runs + self.account_rent
+    }
+}
+
+impl SolanaTransactionManager {
+    // The reserve that must still be left after spending; min_balance_percent is relative
+    // to the starting balance, so it is solved for the balance that leaves exactly that share.
+    fn reserve_after(&self, spend: u64) -> u64 {
+        let transaction = &self.config.transaction;
+        let percent = transaction
+            .min_balance_percent
+            .map(|percent| {
+                // in hundredths of a percent, so a setting like 2.5 survives the integer math
+                let basis_points = (percent.clamp(0.0, 99.0) * 100.0).round() as u128;
+                (spend as u128 * basis_points).div_ceil(10_000 - basis_points) as u64
+            })
+            .unwrap_or_default();
+        transaction.min_balance.max(percent)
+    }
+
+    fn single_funding_plan(&self, sender: &Pubkey, runs: u64) -> Result<FundingPlan> {
+        let mint = self.token_mint()?;
+        let amount = self.amount()?;
+        let fee = self.estimate_transfer_fee(sender, amount)?;
+        let account_rent = match mint {
+            Some(mint) => self.token_account_rent(&self.receiver_pubkey()?, &mint)?,
+            None => 0,
+        };
+
+        Ok(FundingPlan {
+            runs,
+            amount: if mint.is_some() { 0 } else { amount },
+            fee,
+            account_rent,
+        })
+    }
+
+    fn batch_funding_plan(&self, sender: &Pubkey, path: &str, runs: u64) -> Result<FundingPlan> {
+        let recipients = self.load_batch_recipients(path)?;
+        let batches = pack_transactions(sender, &recipients)?;
+
+        Ok(FundingPlan {
+            runs,
+            amount: batch_total(&recipients)?,
+            fee: self.estimate_batch_fees(sender, &batches)?,
+            account_rent: 0,
+        })
+    }
+
+    pub fn print_required_funding(&self, batch: Option<&str>, runs: u64) -> Result<()> {
+        let sender = self.create_sender_keypair()?.pubkey();
+        let plan = match batch {
+            Some(path) => self.batch_funding_plan(&sender, path, runs)?,
+            None => self.single_funding_plan(&sender, runs)?,
+        };
+        let spend = plan.spend();
+        let reserve = self.reserve_after(spend);
+        let required = spend + reserve;
+        let balance = self.get_balance(&sender)?;
+
+        println!("実行回数: {}", plan.runs);
+        println!("1回あたりの送金額: {}", format_sol(plan.amount));
+        println!("1回あたりの手数料 (見積もり): {}", format_sol(plan.fee));
+        println!("アカウント作成のレント: {}", format_sol(plan.account_rent));
+        println!("残しておく額 (min_balance): {}", format_sol(reserve));
+        println!("必要な残高: {}", format_sol(required));
+        println!("現在の残高: {}", format_sol(balance));
+        if balance < required {
+            println!("不足額: {}", format_sol(required - balance));
+        } else {
+            println!("残高は足りています");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::manager;
+
+    #[test]
+    fn reserve_after_leaves_the_percent_of_the_starting_balance() {
+        let whole = manager("min_balance_percent = 20.0", "");
+        // 1000 spent from 1250 leaves 250, which is 20% of 1250
+        assert_eq!(whole.reserve_after(1_000), 250);
+        assert_eq!(whole.reserve_after(1), 1);
+
+        let fractional = manager("min_balance_percent = 2.5", "");
+        assert_eq!(fractional.reserve_after(39_000), 1_000);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{SendOptions, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::time::Duration;
+
+const SLOT_ADVANCE_WAIT: Duration = Duration::from_secs(2);
+const PERFORMANCE_SAMPLES: usize = 5;
+
+impl SolanaTransactionManager {
+    fn network_problem(&self) -> Result<Option<String>> {
+        let samples = self
+            .client
+            .get_recent_performance_samples(Some(PERFORMANCE_SAMPLES))?;
+        let transactions: u64 = samples.iter().map(|sample| sample.num_transactions).sum();
+        let seconds: u64 = samples
+            .iter()
+            .map(|sample| sample.sample_period_secs as u64)
+            .sum();
+        if transactions == 0 {
+            return Ok(Some(format!(
+                "no transactions were processed in the last {} performance samples",
+                samples.len()
+            )));
+        }
+        info!(
+            "クラスタのTPS: {:.0} (直近{}秒)",
+            transactions as f64 / seconds.max(1) as f64,
+            seconds
+        );
+
+        let first = self.client.get_slot()?;
+        std::thread::sleep(SLOT_ADVANCE_WAIT);
+        let second = self.client.get_slot()?;
+        if second <= first {
+            return Ok(Some(format!(
+                "the slot did not advance from {} within {} seconds",
+                first,
+                SLOT_ADVANCE_WAIT.as_secs()
+            )));
+        }
+        info!("スロットが進んでいます: {} -> {}", first, second);
+
+        Ok(None)
+    }
+
+    pub(crate) fn check_network(&self, options: &SendOptions) -> Result<()> {
+        let Some(problem) = self.network_problem()? else {
+            return Ok(());
+        };
+
+        if options.force {
+            warn!(
+                "クラスタが停止している可能性があります: {} (--forceのため続行)",
+                problem
+            );
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "The cluster looks stalled: {}; pass --force to send anyway",
+            problem
+        ))
+    }
+}
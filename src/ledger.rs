@@ -0,0 +1,38 @@
+use anyhow::Result;
+use solana_sdk::signature::Signer;
+
+#[cfg(feature = "ledger")]
+pub fn ledger_signer(account: Option<u32>, change: Option<u32>) -> Result<Box<dyn Signer>> {
+    use solana_remote_wallet::{
+        locator::{Locator, Manufacturer},
+        remote_keypair::generate_remote_keypair,
+        remote_wallet::maybe_wallet_manager,
+    };
+    use solana_sdk::derivation_path::DerivationPath;
+
+    let wallet_manager = maybe_wallet_manager()
+        .map_err(|e| anyhow::anyhow!("Failed to open Ledger: {}", e))?
+        .ok_or_else(|| {
+            anyhow::anyhow!("No Ledger device found; connect it and open the Solana app")
+        })?;
+    let keypair = generate_remote_keypair(
+        Locator {
+            manufacturer: Manufacturer::Ledger,
+            pubkey: None,
+        },
+        DerivationPath::new_bip44(account, change),
+        &wallet_manager,
+        false,
+        "sender",
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to read key from Ledger: {}", e))?;
+
+    Ok(Box::new(keypair))
+}
+
+#[cfg(not(feature = "ledger"))]
+pub fn ledger_signer(_account: Option<u32>, _change: Option<u32>) -> Result<Box<dyn Signer>> {
+    Err(anyhow::anyhow!(
+        "Ledger support is not enabled; rebuild with `--features ledger`"
+    ))
+}
@@ -1,178 +1,1893 @@
+use amount::{parse_sol_arg, sol_decimal, Amount, Rounding};
 use anyhow::{anyhow, Result};
+use base64::Engine;
+use clap::Parser;
+use cli::{Cli, Command};
 use config::Config;
-use log::{error, info};
-use solana_client::rpc_client::RpcClient;
+use error::TransferError;
+use format::format_sol;
+use log::{error, info, warn};
+use pending::PendingMode;
+use solana_client::{
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_config::RpcSendTransactionConfig,
+};
 use solana_program::system_instruction;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
     message::Message,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
+    signature::{Keypair, Signature, Signer},
     transaction::Transaction,
 };
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const MIN_HEAP_FRAME_BYTES: u32 = 32 * 1024;
+const MAX_HEAP_FRAME_BYTES: u32 = 256 * 1024;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+static LOG_LABEL: OnceLock<String> = OnceLock::new();
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+const CONFIG_AUTH_ENV: &str = "SOLANA_TRANSFER_CONFIG_AUTH";
+const CONFIG_TEMPLATE: &str = include_str!("../config/config.toml");
 
-#[derive(Debug, serde_derive::Deserialize)]
+mod amount;
+mod batch;
+mod blockhash;
+mod cli;
+mod confirm;
+mod context;
+mod distribute;
+mod doctor;
+mod dryrun;
+mod error;
+mod explain;
+mod fees;
+mod format;
+mod funding;
+mod health;
+mod ledger;
+mod memo;
+mod multisig;
+mod notify;
+mod payments;
+mod pending;
+mod pipe;
+mod receipts;
+mod recurring;
+mod replay;
+mod rpc;
+mod secret_store;
+mod simulate;
+mod split;
+mod subscribe;
+mod token;
+mod trace;
+mod watch;
+mod webhook;
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 struct Settings {
     network: NetworkConfig,
     keys: KeysConfig,
     transaction: TransactionConfig,
+    token: Option<TokenConfig>,
+    #[serde(default)]
+    output: OutputConfig,
+    receipts: Option<ReceiptsConfig>,
+    notify: Option<NotifyConfig>,
+    multisig: Option<MultisigConfig>,
 }
 
-#[derive(Debug, serde_derive::Deserialize)]
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 struct NetworkConfig {
     rpc_url: String,
+    ws_url: Option<String>,
+    #[serde(default)]
+    fallback_rpc_urls: Vec<String>,
+    balance_commitment: Option<CommitmentLevel>,
+    #[serde(default)]
+    read_min_context_slot: bool,
+    max_requests_per_second: Option<u32>,
+    #[serde(default)]
+    broadcast_to_all: bool,
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+    #[serde(default)]
+    tls_cert_sha256: Vec<String>,
+    rate_limit_retries: Option<u32>,
+    max_retry_after: Option<u64>,
+}
+
+impl NetworkConfig {
+    fn rpc_urls(&self) -> Vec<String> {
+        let mut rpc_urls = vec![self.rpc_url.clone()];
+        rpc_urls.extend(self.fallback_rpc_urls.iter().cloned());
+        rpc_urls
+    }
 }
 
-#[derive(Debug, serde_derive::Deserialize)]
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 struct KeysConfig {
-    sender_private_key: String,
-    receiver_public_key: String,
+    #[serde(serialize_with = "redact")]
+    sender_private_key: Option<String>,
+    keyring_service: Option<String>,
+    keyring_user: Option<String>,
+    #[serde(default)]
+    key_encoding: KeyEncoding,
+    #[serde(default)]
+    ledger: bool,
+    ledger_account: Option<u32>,
+    ledger_change: Option<u32>,
+    receiver_public_key: Option<String>,
+    receiver_keypair_path: Option<String>,
+}
+
+fn redact<S: serde::Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_some(&value.as_ref().map(|_| "<redacted>"))
 }
 
-#[derive(Debug, serde_derive::Deserialize)]
+#[derive(Debug, Default, Clone, Copy, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum KeyEncoding {
+    #[default]
+    Base58,
+    Base64,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, serde_derive::Deserialize, serde_derive::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+enum SuccessCriteria {
+    #[default]
+    SignatureStatus,
+    BalanceVerified,
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 struct TransactionConfig {
-    amount: u64,
+    label: Option<String>,
+    amount: Amount,
+    #[serde(default)]
+    rounding: Rounding,
     min_balance: u64,
+    min_balance_percent: Option<f64>,
+    low_balance_threshold: Option<u64>,
+    #[serde(default)]
+    clamp_to_balance: bool,
     confirmation_timeout: u64,
+    poll_interval_ms: Option<u64>,
+    finalize_timeout: Option<u64>,
+    dust_threshold: Option<u64>,
+    large_transfer_threshold: Option<u64>,
+    approval_webhook_url: Option<String>,
+    approval_timeout: Option<u64>,
+    max_total_cost: Option<u64>,
+    reference: Option<String>,
+    max_sign_retries: Option<u32>,
+    pending_state_file: Option<String>,
+    heap_bytes: Option<u32>,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+    #[serde(default)]
+    auto_priority_fee: bool,
+    #[serde(default)]
+    adaptive_priority_fee: bool,
+    priority_fee_floor: Option<u64>,
+    fee_wait_threshold: Option<u64>,
+    fee_wait_timeout: Option<u64>,
+    fee_wait_interval: Option<u64>,
+    fee_escalation_step: Option<u64>,
+    fee_escalation_cap: Option<u64>,
+    fee_escalation_attempts: Option<u32>,
+    min_context_slot: Option<u64>,
+    submit_before_slot: Option<u64>,
+    slot_ttl: Option<u64>,
+    #[serde(default)]
+    blocked_receivers: Vec<String>,
+    blocked_receivers_file: Option<String>,
+    duplicate_window: Option<u64>,
+    duplicate_scan_limit: Option<usize>,
+    #[serde(default)]
+    abort_on_duplicate: bool,
+    #[serde(default)]
+    duplicate_recipients: batch::DuplicateRecipients,
+    #[serde(default)]
+    stop_on_error: bool,
+    #[serde(default)]
+    success_criteria: SuccessCriteria,
+    split_into: Option<u32>,
+    blockhash_refresh_secs: Option<u64>,
+    #[serde(default)]
+    confirm_by_account: bool,
+}
+
+#[derive(Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+struct OutputConfig {
+    #[serde(default)]
+    show_balance_delta: bool,
+    #[serde(default)]
+    show_rent_report: bool,
+    success_template: Option<String>,
+    #[serde(default)]
+    explain: bool,
+    #[serde(default)]
+    trace_tx: bool,
+    signature_file: Option<String>,
+    #[serde(default)]
+    table: bool,
+    #[serde(default)]
+    json: bool,
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct ReceiptsConfig {
+    #[serde(default = "default_receipts_path")]
+    path: String,
+    rotate_bytes: Option<u64>,
+}
+
+fn default_receipts_path() -> String {
+    "receipts.jsonl".to_string()
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct NotifyConfig {
+    webhook_url: String,
+    #[serde(default = "notify::default_events")]
+    events: Vec<notify::NotifyEvent>,
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct MultisigConfig {
+    address: String,
+    #[serde(default)]
+    program: multisig::MultisigProgram,
+    #[serde(default)]
+    vault_index: u8,
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct TokenConfig {
+    mint: String,
+    sender_token_account: Option<String>,
+    #[serde(default)]
+    receiver_is_pda: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SendOptions {
+    force: bool,
+    allow_zero: bool,
+    track_finalized: bool,
+    network_check: bool,
+    skip_duplicate_check: bool,
+    yes: bool,
+    yes_large: bool,
+    // set for split chunks: max_total_cost, the large-transfer prompt and approval already ran
+    // once against the whole split
+    whole_transfer_checked: bool,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct TransferSummary {
+    sender: String,
+    receiver: String,
+    amount: u64,
+    mint: Option<String>,
+    fee: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+#[derive(Debug)]
+struct TransferOutcome {
+    signature: Signature,
+    summary: TransferSummary,
+    balance_before: u64,
+    balance_after: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct TransferInputs {
+    // micro-lamports per compute unit; None leaves the priority fee off
+    priority_fee: Option<u64>,
+    // mint decimals for transfer_checked; None for SOL transfers
+    decimals: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TransferCost {
+    amount: u64,
+    // get_fee_for_message includes any compute-budget priority fee
+    fee: u64,
+    account_rent: u64,
+}
+
+impl TransferCost {
+    fn total(&self) -> u64 {
+        self.amount + self.fee + self.account_rent
+    }
+}
+
+#[derive(Debug)]
+struct BalanceSnapshot {
+    sender: u64,
+    receiver: Option<u64>,
 }
 
 struct SolanaTransactionManager {
     config: Settings,
     client: RpcClient,
+    async_client: solana_client::nonblocking::rpc_client::RpcClient,
+    rpc_stats: rpc::SharedStats,
+    endpoint_options: rpc::EndpointOptions,
+    rate_limiter: Option<rpc::SharedLimiter>,
+    background: std::sync::Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    blockhash_heights: std::sync::Mutex<std::collections::HashMap<Hash, u64>>,
+    mints: std::sync::Mutex<std::collections::HashMap<Pubkey, spl_token::state::Mint>>,
+    fixed_blockhash: Option<Hash>,
+    cached_blockhash: std::sync::Mutex<Option<blockhash::CachedBlockhash>>,
+    signed_messages: std::sync::Mutex<std::collections::HashSet<Hash>>,
+    slots: context::SlotTracker,
+    account_watch: std::sync::Mutex<Option<subscribe::AccountWatch>>,
+    pending_mode: pending::PendingMode,
 }
 
 impl SolanaTransactionManager {
-    pub fn new(config_path: &str) -> Result<Self> {
-        let settings = Self::load_config(config_path)?;
-        let client = RpcClient::new_with_timeout(
-            settings.network.rpc_url.clone(),
-            Duration::from_secs(30),
+    pub fn new(config_paths: &[String]) -> Result<Self> {
+        Self::with_settings(Self::load_config(config_paths)?)
+    }
+
+    fn with_settings(settings: Settings) -> Result<Self> {
+        settings.network.check_endpoints()?;
+        let endpoint_options = settings.network.endpoint_options()?;
+        let rpc_urls = settings.network.rpc_urls();
+        let rate_limiter = rpc::rate_limiter(settings.network.max_requests_per_second);
+        let sender =
+            rpc::RateLimitedSender::new(&rpc_urls, rate_limiter.clone(), &endpoint_options)?;
+        let rpc_stats = sender.stats();
+        let client = RpcClient::new_sender(
+            sender,
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let async_client = solana_client::nonblocking::rpc_client::RpcClient::new_sender(
+            rpc::RateLimitedSender::with_stats(
+                &rpc_urls,
+                rate_limiter.clone(),
+                &endpoint_options,
+                rpc_stats.clone(),
+            )?,
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
         );
 
         Ok(Self {
             config: settings,
             client,
+            async_client,
+            rpc_stats,
+            endpoint_options,
+            rate_limiter,
+            background: Default::default(),
+            blockhash_heights: Default::default(),
+            mints: Default::default(),
+            fixed_blockhash: None,
+            cached_blockhash: Default::default(),
+            signed_messages: Default::default(),
+            slots: Default::default(),
+            account_watch: Default::default(),
+            pending_mode: Default::default(),
         })
     }
 
-    fn load_config(config_path: &str) -> Result<Settings> {
-        let settings = Config::builder()
-            .add_source(config::File::with_name(config_path))
-            .build()?;
+    fn load_config(config_paths: &[String]) -> Result<Settings> {
+        let mut builder = Config::builder();
+        for config_path in config_paths {
+            if config_path.starts_with("http://") || config_path.starts_with("https://") {
+                builder = builder.add_source(Self::remote_config(config_path)?);
+            } else {
+                builder = builder.add_source(Self::config_file(config_path)?);
+            }
+        }
+
+        let settings = builder
+            .build()
+            .map_err(|e| TransferError::Config(e.to_string()))?;
+
+        Ok(serde_path_to_error::deserialize(settings)
+            .map_err(|e| TransferError::Config(describe_config_error(e)))?)
+    }
+
+    fn remote_config(
+        url: &str,
+    ) -> Result<config::File<config::FileSourceString, config::FileFormat>> {
+        let authorization = std::env::var(CONFIG_AUTH_ENV).ok();
+        let body = webhook::get_text(url, authorization.as_deref(), Duration::from_secs(30))
+            .map_err(|e| TransferError::Config(e.to_string()))?;
 
-        Ok(settings.try_deserialize()?)
+        let source = config::File::from_str(&body, config::FileFormat::Toml);
+        Config::builder()
+            .add_source(source.clone())
+            .build()
+            .map_err(|e| TransferError::Config(format!("Invalid config from {}: {}", url, e)))?;
+
+        Ok(source)
+    }
+
+    fn config_file(
+        config_path: &str,
+    ) -> Result<config::File<config::FileSourceFile, config::FileFormat>> {
+        if !Path::new(config_path).exists() {
+            return Err(TransferError::Config(format!(
+                "Config file not found: {}\n\
+                 Required fields:\n  \
+                 [network] rpc_url\n  \
+                 [keys] receiver_public_key (or receiver_keypair_path) and sender_private_key (or keyring_service + keyring_user)\n  \
+                 [transaction] amount, min_balance, confirmation_timeout\n\
+                 Run `solana-transfer generate-config` to create a template",
+                std::env::current_dir()
+                    .map(|dir| dir.join(config_path))
+                    .unwrap_or_else(|_| config_path.into())
+                    .display()
+            ))
+            .into());
+        }
+
+        Ok(config::File::with_name(config_path))
+    }
+
+    fn balance_commitment(&self) -> CommitmentConfig {
+        CommitmentConfig {
+            commitment: self
+                .config
+                .network
+                .balance_commitment
+                .unwrap_or(CommitmentLevel::Confirmed),
+        }
     }
 
     fn get_balance(&self, pubkey: &Pubkey) -> Result<u64> {
-        let balance = self.client.get_balance(pubkey)?;
-        Ok(balance)
+        self.balance_with_context(pubkey, self.balance_commitment())
+    }
+
+    fn check_receiver_allowed(&self, receiver: &Pubkey) -> Result<()> {
+        let transaction = &self.config.transaction;
+        let mut blocked = transaction.blocked_receivers.clone();
+        if let Some(path) = &transaction.blocked_receivers_file {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| TransferError::Config(format!("Failed to read {}: {}", path, e)))?;
+            blocked.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let receiver = receiver.to_string();
+        if blocked.contains(&receiver) {
+            return Err(TransferError::BlockedReceiver { receiver }.into());
+        }
+        Ok(())
+    }
+
+    fn check_sender_owner(&self, sender: &Pubkey) {
+        if let Ok(account) = self.client.get_account(sender) {
+            if account.owner != solana_program::system_program::id() {
+                warn!(
+                    "送信元 {} はSystem Programではなく {} が所有しています (ノンスやトークンアカウントの鍵では?)。SOL送金は失敗する可能性があります",
+                    sender, account.owner
+                );
+            }
+        }
+    }
+
+    // The reserve left on the sender: min_balance, or min_balance_percent of the current
+    // balance when that is larger.
+    fn min_balance(&self, balance: u64) -> u64 {
+        let percent = self
+            .config
+            .transaction
+            .min_balance_percent
+            .map(|percent| (balance as f64 * percent.clamp(0.0, 100.0) / 100.0).ceil() as u64)
+            .unwrap_or_default();
+        self.config.transaction.min_balance.max(percent)
     }
 
     fn check_sufficient_balance(&self, sender_pubkey: &Pubkey, amount: u64) -> Result<bool> {
         let balance = self.get_balance(sender_pubkey)?;
-        Ok(balance >= amount + self.config.transaction.min_balance)
+        Ok(balance >= amount + self.min_balance(balance))
     }
 
-    pub fn send_transaction(&self) -> Result<String> {
-        let sender_keypair = self.create_sender_keypair()?;
-        
-        let receiver_pubkey = Pubkey::from_str(&self.config.keys.receiver_public_key)
-            .map_err(|e| anyhow!("Invalid receiver public key: {}", e))?;
+    fn receiver_pubkey(&self) -> Result<Pubkey> {
+        let keys = &self.config.keys;
+        match (&keys.receiver_public_key, &keys.receiver_keypair_path) {
+            (Some(pubkey), None) => Ok(Pubkey::from_str(pubkey).map_err(|e| {
+                TransferError::Config(format!("Invalid receiver public key: {}", e))
+            })?),
+            (None, Some(path)) => Ok(solana_sdk::signature::read_keypair_file(path)
+                .map_err(|e| {
+                    TransferError::Config(format!(
+                        "Failed to read receiver keypair {}: {}",
+                        path, e
+                    ))
+                })?
+                .pubkey()),
+            (Some(_), Some(_)) => Err(TransferError::Config(
+                "Set either receiver_public_key or receiver_keypair_path, not both".to_string(),
+            )
+            .into()),
+            (None, None) => Err(TransferError::Config(
+                "receiver_public_key or receiver_keypair_path is not configured".to_string(),
+            )
+            .into()),
+        }
+    }
 
-        let current_balance = self.get_balance(&sender_keypair.pubkey())?;
-        info!(
-            "現在の残高: {} SOL",
-            (current_balance as f64) / 1_000_000_000.0
+    // Solana Pay style: the reference is attached as a read-only, non-signer account on the
+    // transfer instruction so the payment can be found with get_signatures_for_address(reference).
+    fn reference(&self) -> Result<Option<Pubkey>> {
+        self.config
+            .transaction
+            .reference
+            .as_ref()
+            .map(|reference| {
+                Pubkey::from_str(reference).map_err(|e| {
+                    TransferError::Config(format!("Invalid reference pubkey: {}", e)).into()
+                })
+            })
+            .transpose()
+    }
+
+    fn check_dust(&self, lamports: u64, fee: u64, options: &SendOptions) -> Result<()> {
+        let reason = if lamports < fee {
+            format!("is below the estimated fee of {}", format_sol(fee))
+        } else {
+            match self.config.transaction.dust_threshold {
+                Some(threshold) if lamports < threshold => {
+                    format!("is below the dust threshold of {}", format_sol(threshold))
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        if options.force {
+            warn!(
+                "送金額 {} {} (--forceのため続行)",
+                format_sol(lamports),
+                reason
+            );
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "Transfer amount {} {}; pass --force to send anyway",
+            format_sol(lamports),
+            reason
+        ))
+    }
+
+    // Above large_transfer_threshold a plain "y" is not enough: the amount itself has to be
+    // typed back, and --yes does not skip it. Only --yes-large does.
+    fn confirm_large_transfer(&self, lamports: u64, options: &SendOptions) -> Result<()> {
+        let Some(threshold) = self.config.transaction.large_transfer_threshold else {
+            return Ok(());
+        };
+        if lamports <= threshold {
+            return Ok(());
+        }
+        if options.yes_large {
+            warn!(
+                "送金額 {} がlarge_transfer_threshold {} を超えています (--yes-largeのため確認を省略)",
+                format_sol(lamports),
+                format_sol(threshold)
+            );
+            return Ok(());
+        }
+        if !std::io::stdin().is_terminal() {
+            return Err(anyhow!(
+                "Transfer amount {} exceeds large_transfer_threshold {} and stdin is not a terminal; pass --yes-large to send anyway",
+                format_sol(lamports),
+                format_sol(threshold)
+            ));
+        }
+
+        println!(
+            "送金額 {} はlarge_transfer_threshold {} を超えています。",
+            format_sol(lamports),
+            format_sol(threshold)
+        );
+        println!(
+            "送金する場合は金額をSOLで入力してください (例: {}):",
+            sol_decimal(lamports)
         );
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if parse_sol_arg(&answer) != Ok(lamports) {
+            return Err(anyhow!(
+                "Entered amount '{}' does not match the transfer amount {}; aborted",
+                answer.trim(),
+                format_sol(lamports)
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_total_cost(&self, cost: &TransferCost) -> Result<()> {
+        let Some(limit) = self.config.transaction.max_total_cost else {
+            return Ok(());
+        };
+
+        if cost.total() > limit {
+            return Err(anyhow!(
+                "Total cost {} (amount {} + fee {} + account rent {}) exceeds max_total_cost {}",
+                format_sol(cost.total()),
+                format_sol(cost.amount),
+                format_sol(cost.fee),
+                format_sol(cost.account_rent),
+                format_sol(limit)
+            ));
+        }
+
+        Ok(())
+    }
 
-        if !self.check_sufficient_balance(&sender_keypair.pubkey(), self.config.transaction.amount)? {
+    fn request_approval(&self, url: &str, summary: &TransferSummary) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.transaction.approval_timeout.unwrap_or(120));
+        info!("承認待ち: {}", url);
+
+        let status = webhook::post_json(url, summary, timeout)
+            .map_err(|e| anyhow!("Transfer was not approved: {}", e))?;
+        if status != reqwest::StatusCode::OK {
             return Err(anyhow!(
-                "Insufficient balance. Current balance: {} SOL, Required: {} SOL",
-                (current_balance as f64) / 1_000_000_000.0,
-                ((self.config.transaction.amount + self.config.transaction.min_balance) as f64)
-                    / 1_000_000_000.0
+                "Transfer was not approved: webhook returned {}",
+                status
             ));
         }
 
-        let instruction = system_instruction::transfer(
+        info!("承認されました");
+        Ok(())
+    }
+
+    // The checks every transfer passes right before it is signed, whichever path builds it.
+    // `sender` is the account the funds leave, which is the vault for a multisig proposal.
+    fn preflight(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        mint: Option<&Pubkey>,
+        summary: &TransferSummary,
+        cost: &TransferCost,
+        options: &SendOptions,
+    ) -> Result<()> {
+        if !options.skip_duplicate_check {
+            self.check_recent_duplicate(sender, receiver, mint, summary.amount, options)?;
+        }
+        if mint.is_none() {
+            self.check_dust(cost.amount, cost.fee, options)?;
+        }
+        if options.whole_transfer_checked {
+            return Ok(());
+        }
+
+        self.check_total_cost(cost)?;
+        if mint.is_none() {
+            self.confirm_large_transfer(cost.amount, options)?;
+        }
+        if let Some(url) = &self.config.transaction.approval_webhook_url {
+            self.request_approval(url, summary)?;
+        }
+        Ok(())
+    }
+
+    fn compute_budget_instructions(&self, priority_fee: Option<u64>) -> Result<Vec<Instruction>> {
+        let mut instructions = Vec::new();
+
+        if let Some(heap_bytes) = self.config.transaction.heap_bytes {
+            if heap_bytes % 1024 != 0
+                || !(MIN_HEAP_FRAME_BYTES..=MAX_HEAP_FRAME_BYTES).contains(&heap_bytes)
+            {
+                return Err(TransferError::Config(format!(
+                    "heap_bytes must be a multiple of 1024 between {} and {}, got {}",
+                    MIN_HEAP_FRAME_BYTES, MAX_HEAP_FRAME_BYTES, heap_bytes
+                ))
+                .into());
+            }
+            instructions.push(ComputeBudgetInstruction::request_heap_frame(heap_bytes));
+        }
+
+        if let Some(limit) = self.config.transaction.compute_unit_limit {
+            if limit == 0 || limit > MAX_COMPUTE_UNIT_LIMIT {
+                return Err(TransferError::Config(format!(
+                    "compute_unit_limit must be between 1 and {}, got {}",
+                    MAX_COMPUTE_UNIT_LIMIT, limit
+                ))
+                .into());
+            }
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+        }
+
+        if let Some(price) = priority_fee.filter(|price| *price > 0) {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+
+        Ok(instructions)
+    }
+
+    fn use_rent_exempt_amount(&mut self, space: usize) -> Result<()> {
+        if self.config.token.is_some() {
+            return Err(TransferError::Config(
+                "--init-account-space supports SOL transfers only".to_string(),
+            )
+            .into());
+        }
+
+        let lamports = self.client.get_minimum_balance_for_rent_exemption(space)?;
+        info!(
+            "{}バイトのアカウントのレント免除最低額: {}",
+            space,
+            format_sol(lamports)
+        );
+        self.config.transaction.amount = Amount::Lamports(lamports);
+
+        Ok(())
+    }
+
+    fn clamp_to_balance(&self, sender: &Pubkey, amount: u64, balance: u64) -> Result<u64> {
+        let fee = self.estimate_transfer_fee(sender, amount)?;
+        let reserved = fee + self.min_balance(balance);
+        let available = balance.saturating_sub(reserved);
+        if amount <= available {
+            return Ok(amount);
+        }
+        if available == 0 {
+            return Err(TransferError::InsufficientBalance {
+                balance,
+                required: reserved + 1,
+            }
+            .into());
+        }
+
+        warn!(
+            "残高が足りないため送金額を {} から {} に減らします (clamp_to_balance)",
+            format_sol(amount),
+            format_sol(available)
+        );
+        Ok(available)
+    }
+
+    fn amount(&self) -> Result<u64> {
+        self.config
+            .transaction
+            .amount
+            .resolve(self.config.transaction.rounding)
+            .map_err(|e| TransferError::Config(e.to_string()).into())
+    }
+
+    // The network-dependent parts of a transfer, fetched once per send so the instructions are
+    // built from them without further RPC calls.
+    fn transfer_inputs(&self, sender: &Pubkey) -> Result<TransferInputs> {
+        let receiver = self.receiver_pubkey()?;
+        Ok(TransferInputs {
+            priority_fee: self.priority_fee(&[*sender, receiver])?,
+            decimals: self
+                .token_mint()?
+                .map(|mint| self.mint_decimals(&mint))
+                .transpose()?,
+        })
+    }
+
+    pub fn build_transfer_instructions(
+        &self,
+        sender: &Pubkey,
+        amount: u64,
+        inputs: &TransferInputs,
+    ) -> Result<Vec<Instruction>> {
+        let receiver = self.receiver_pubkey()?;
+        let mut instructions = self.compute_budget_instructions(inputs.priority_fee)?;
+        match self.token_mint()? {
+            Some(mint) => {
+                let decimals = inputs
+                    .decimals
+                    .ok_or_else(|| anyhow!("Mint decimals are required for a token transfer"))?;
+                instructions.extend(
+                    self.token_transfer_instructions(sender, &receiver, &mint, amount, decimals)?,
+                )
+            }
+            None => instructions.push(system_instruction::transfer(sender, &receiver, amount)),
+        }
+
+        if let Some(reference) = self.reference()? {
+            if let Some(transfer) = instructions.last_mut() {
+                transfer
+                    .accounts
+                    .push(AccountMeta::new_readonly(reference, false));
+            }
+        }
+
+        Ok(instructions)
+    }
+
+    pub fn build_transfer_message(
+        &self,
+        sender: &Pubkey,
+        amount: u64,
+        inputs: &TransferInputs,
+        blockhash: Hash,
+    ) -> Result<Message> {
+        Ok(Message::new_with_blockhash(
+            &self.build_transfer_instructions(sender, amount, inputs)?,
+            Some(sender),
+            &blockhash,
+        ))
+    }
+
+    // The unsigned transfer over the latest blockhash, for fee estimates and inspection.
+    fn transfer_message(&self, sender: &Pubkey, amount: u64) -> Result<Message> {
+        let inputs = self.transfer_inputs(sender)?;
+        self.build_transfer_message(sender, amount, &inputs, self.client.get_latest_blockhash()?)
+    }
+
+    fn estimate_transfer_fee(&self, sender: &Pubkey, amount: u64) -> Result<u64> {
+        let message = self.transfer_message(sender, amount)?;
+        Ok(self.client.get_fee_for_message(&message)?)
+    }
+
+    pub fn send_transaction(&self, options: &SendOptions) -> Result<TransferOutcome> {
+        let deadline = self.submit_deadline()?;
+        if options.network_check {
+            self.check_network(options)?;
+        }
+        let sender_keypair = self.create_sender_keypair()?;
+
+        let receiver_pubkey = self.receiver_pubkey()?;
+        self.check_receiver_allowed(&receiver_pubkey)?;
+
+        let current_balance = self.get_balance(&sender_keypair.pubkey())?;
+        info!("現在の残高: {}", format_sol(current_balance));
+        self.check_low_balance(&sender_keypair.pubkey(), current_balance);
+
+        let mint = self.token_mint()?;
+        let mut amount = self.amount()?;
+        if mint.is_none() && self.config.transaction.clamp_to_balance {
+            amount = self.clamp_to_balance(&sender_keypair.pubkey(), amount, current_balance)?;
+        }
+        let lamports = if mint.is_some() { 0 } else { amount };
+        if amount == 0 && !options.allow_zero {
+            return Err(TransferError::Config(
+                "amount is 0; pass --allow-zero to send a zero-amount transaction anyway"
+                    .to_string(),
+            )
+            .into());
+        }
+        if mint.is_none() {
+            self.check_sender_owner(&sender_keypair.pubkey());
+        }
+
+        if mint.is_none() && self.config.output.show_rent_report {
+            self.print_rent_report(&receiver_pubkey, lamports)?;
+        }
+
+        if !self.check_sufficient_balance(&sender_keypair.pubkey(), lamports)? {
+            return Err(TransferError::InsufficientBalance {
+                balance: current_balance,
+                required: lamports + self.min_balance(current_balance),
+            }
+            .into());
+        }
+
+        if let Some(mint) = mint {
+            self.check_token_transfer(&sender_keypair.pubkey(), &receiver_pubkey, &mint, amount)?;
+        }
+        self.wait_for_fee_market(&[sender_keypair.pubkey(), receiver_pubkey])?;
+        let inputs = self.transfer_inputs(&sender_keypair.pubkey())?;
+        let message = self.build_transfer_message(
+            &sender_keypair.pubkey(),
+            amount,
+            &inputs,
+            self.client.get_latest_blockhash()?,
+        )?;
+
+        let fee = self.client.get_fee_for_message(&message)?;
+        let required = lamports + fee + self.min_balance(current_balance);
+        if current_balance < required {
+            return Err(TransferError::InsufficientBalance {
+                balance: current_balance,
+                required,
+            }
+            .into());
+        }
+        let account_rent = match mint {
+            Some(mint) => self.token_account_rent(&receiver_pubkey, &mint)?,
+            None => 0,
+        };
+        let cost = TransferCost {
+            amount: lamports,
+            fee,
+            account_rent,
+        };
+        if self.config.output.explain {
+            self.explain_preview(&explain::Preview {
+                sender: sender_keypair.pubkey(),
+                receiver: receiver_pubkey,
+                balance: current_balance,
+                amount,
+                mint,
+                fee,
+                account_rent,
+                min_balance: self.min_balance(current_balance),
+                receiver_exists: self.client.get_account(&receiver_pubkey).is_ok(),
+            });
+        }
+
+        let summary = TransferSummary {
+            sender: sender_keypair.pubkey().to_string(),
+            receiver: receiver_pubkey.to_string(),
+            amount,
+            mint: mint.map(|mint| mint.to_string()),
+            fee,
+            label: self.config.transaction.label.clone(),
+        };
+
+        self.preflight(
             &sender_keypair.pubkey(),
             &receiver_pubkey,
-            self.config.transaction.amount,
+            mint.as_ref(),
+            &summary,
+            &cost,
+            options,
+        )?;
+
+        // Signed over the same instructions as the fee message, with the signing blockhash.
+        let instructions =
+            self.build_transfer_instructions(&sender_keypair.pubkey(), amount, &inputs)?;
+        let transaction = self.sign_transaction(&instructions, sender_keypair.as_ref())?;
+
+        let before =
+            self.balance_snapshot(&sender_keypair.pubkey(), &receiver_pubkey, mint, None)?;
+        if mint.is_none() {
+            self.watch_receiver(&receiver_pubkey, lamports);
+        }
+        let result = self.submit_with_fee_escalation(
+            transaction,
+            &instructions,
+            sender_keypair.as_ref(),
+            amount,
+            cost,
+            deadline,
         );
+        self.stop_watching_receiver();
+        let (signature, landed_fee) = result?;
 
-        let recent_blockhash = self.client.get_latest_blockhash()?;
+        info!("TX送信成功 - シグネチャ: {}", signature);
+        if options.track_finalized {
+            self.track_finalization(signature);
+        }
+        self.record_receipt(&signature, &summary);
+        self.record_signature(&signature);
+
+        let new_balance = self.get_balance(&sender_keypair.pubkey())?;
+        info!("変異後残高: {}", format_sol(new_balance));
+
+        if self.config.output.show_balance_delta {
+            let debited = current_balance.saturating_sub(new_balance);
+            println!(
+                "送金額: {} / 引き落とし総額: {} / 手数料: {}",
+                format_sol(lamports),
+                format_sol(debited),
+                format_sol(
+                    debited
+                        .saturating_sub(lamports)
+                        .saturating_sub(cost.account_rent)
+                )
+            );
+            if cost.account_rent > 0 {
+                println!("アカウント作成のレント: {}", format_sol(cost.account_rent));
+            }
+        }
+
+        let landed_slot = self.client.get_signature_statuses(&[signature])?.value[0]
+            .as_ref()
+            .map(|status| status.slot);
+        let after = self.balance_snapshot(
+            &sender_keypair.pubkey(),
+            &receiver_pubkey,
+            mint,
+            landed_slot,
+        )?;
+        let landed_cost = TransferCost {
+            fee: landed_fee,
+            ..cost
+        };
+        if !self.reconcile_transfer(&before, &after, &landed_cost)
+            && self.config.transaction.success_criteria == SuccessCriteria::BalanceVerified
+        {
+            return Err(anyhow!(
+                "Transaction {} was confirmed but the balances did not change as expected (success_criteria = balance-verified)",
+                signature
+            ));
+        }
+
+        Ok(TransferOutcome {
+            signature,
+            summary,
+            balance_before: current_balance,
+            balance_after: new_balance,
+        })
+    }
+
+    fn success_message(&self, outcome: &TransferOutcome) -> String {
+        self.render_success(outcome, format::units())
+    }
+
+    fn render_success(&self, outcome: &TransferOutcome, units: format::Units) -> String {
+        let template = self
+            .config
+            .output
+            .success_template
+            .as_deref()
+            .unwrap_or("TX成功!: {signature}");
+        let summary = &outcome.summary;
+        let amount = match summary.mint {
+            Some(_) => summary.amount.to_string(),
+            None => format::format_amount(summary.amount, units),
+        };
+
+        format::render_template(
+            template,
+            &[
+                ("sender", summary.sender.clone()),
+                ("receiver", summary.receiver.clone()),
+                ("amount", amount),
+                ("signature", outcome.signature.to_string()),
+                ("fee", format::format_amount(summary.fee, units)),
+                (
+                    "balance_before",
+                    format::format_amount(outcome.balance_before, units),
+                ),
+                (
+                    "balance_after",
+                    format::format_amount(outcome.balance_after, units),
+                ),
+            ],
+        )
+    }
+
+    fn print_rent_report(&self, receiver: &Pubkey, lamports: u64) -> Result<()> {
+        let account = self
+            .client
+            .get_account_with_commitment(receiver, CommitmentConfig::confirmed())?
+            .value;
+        let data_len = account.as_ref().map_or(0, |account| account.data.len());
+        let balance = account.as_ref().map_or(0, |account| account.lamports);
+        let minimum = self
+            .client
+            .get_minimum_balance_for_rent_exemption(data_len)?;
+
+        println!("受取アカウントのレント情報:");
+        println!(
+            "  状態: {}",
+            if account.is_some() {
+                "既存アカウント"
+            } else {
+                "新規アカウント"
+            }
+        );
+        println!(
+            "  レント免除の最低残高 ({} bytes): {}",
+            data_len,
+            format_sol(minimum)
+        );
+        println!("  現在の残高: {}", format_sol(balance));
+        println!("  送金後の残高: {}", format_sol(balance + lamports));
+        if balance + lamports >= minimum {
+            println!("  送金後はレント免除の最低残高を満たします");
+        } else {
+            println!(
+                "  送金後もレント免除の最低残高に {} 足りません (新規アカウントへの送金は失敗します)",
+                format_sol(minimum - balance - lamports)
+            );
+        }
+
+        Ok(())
+    }
+
+    // Read at the balance commitment; `landed_slot` keeps the read after a transfer from a bank
+    // that does not include it yet.
+    fn balance_snapshot(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        mint: Option<Pubkey>,
+        landed_slot: Option<u64>,
+    ) -> Result<BalanceSnapshot> {
+        let balance = |pubkey: &Pubkey| match landed_slot {
+            Some(slot) => self.balance_since_slot(pubkey, slot),
+            None => self.get_balance(pubkey),
+        };
+        Ok(BalanceSnapshot {
+            sender: balance(sender)?,
+            receiver: match mint {
+                Some(_) => None,
+                None => Some(balance(receiver)?),
+            },
+        })
+    }
+
+    fn reconcile_transfer(
+        &self,
+        before: &BalanceSnapshot,
+        after: &BalanceSnapshot,
+        cost: &TransferCost,
+    ) -> bool {
+        let mut matched = true;
+        let debited = before.sender as i128 - after.sender as i128;
+        if debited != cost.total() as i128 {
+            matched = false;
+            warn!(
+                "送信元の引き落とし額が想定と一致しません: 実際 {} lamports / 想定 {} lamports (差分 {} lamports)",
+                debited,
+                cost.total(),
+                debited - cost.total() as i128
+            );
+        }
+
+        if let (Some(before), Some(after)) = (before.receiver, after.receiver) {
+            let received = after as i128 - before as i128;
+            if received != cost.amount as i128 {
+                matched = false;
+                warn!(
+                    "受取側の増加額が想定と一致しません: 実際 {} lamports / 想定 {} lamports (差分 {} lamports)",
+                    received,
+                    cost.amount,
+                    received - cost.amount as i128
+                );
+            }
+        }
+
+        matched
+    }
+
+    fn sign_transaction(
+        &self,
+        instructions: &[Instruction],
+        signer: &dyn Signer,
+    ) -> Result<Transaction> {
+        let attempts = self.config.transaction.max_sign_retries.unwrap_or(3) + 1;
+
+        let mut attempt = 1;
+        loop {
+            match self.try_sign_transaction(instructions, signer) {
+                Ok(transaction) => return Ok(transaction),
+                Err(e) if attempt < attempts => {
+                    warn!("署名をやり直します ({}/{}): {}", attempt, attempts, e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn try_sign_transaction(
+        &self,
+        instructions: &[Instruction],
+        signer: &dyn Signer,
+    ) -> Result<Transaction> {
+        let message = self.unique_message(instructions, &signer.pubkey())?;
+        let blockhash = message.recent_blockhash;
 
-        let message = Message::new(&[instruction], Some(&sender_keypair.pubkey()));
         let mut transaction = Transaction::new_unsigned(message);
-        transaction.sign(&[&sender_keypair], recent_blockhash);
+        if self.config.keys.ledger {
+            println!("Ledgerでトランザクションを確認して承認してください");
+        }
+        transaction.try_sign(&[signer], blockhash)?;
 
-        let signature = self
+        let valid = self
             .client
-            .send_and_confirm_transaction_with_spinner_and_config(
-                &transaction,
-                CommitmentConfig::confirmed(),
-                solana_client::rpc_config::RpcSendTransactionConfig {
-                    skip_preflight: true,
-                    preflight_commitment: None,
-                    encoding: None,
-                    max_retries: None,
-                    min_context_slot: None,
-                },
-            )?;
+            .is_blockhash_valid(&blockhash, CommitmentConfig::processed())?;
+        if !valid && self.fixed_blockhash.is_some() {
+            warn!(
+                "指定したブロックハッシュ {} は有効ではありません。送信は拒否される可能性があります",
+                blockhash
+            );
+        } else if !valid {
+            self.invalidate_cached_blockhash(&blockhash);
+            return Err(anyhow!("Blockhash {} expired before submission", blockhash));
+        }
 
-        info!("TX送信成功 - シグネチャ: {}", signature);
+        Ok(transaction)
+    }
 
-        let new_balance = self.get_balance(&sender_keypair.pubkey())?;
-        info!(
-            "変異後残高: {} SOL",
-            (new_balance as f64) / 1_000_000_000.0
-        );
+    pub(crate) fn submit_deadline(&self) -> Result<Option<u64>> {
+        let ttl_deadline = match self.config.transaction.slot_ttl {
+            Some(ttl) => Some(self.client.get_slot()?.saturating_add(ttl)),
+            None => None,
+        };
 
-        Ok(signature.to_string())
+        Ok(
+            match (self.config.transaction.submit_before_slot, ttl_deadline) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        )
     }
 
-    fn create_sender_keypair(&self) -> Result<Keypair> {
-        let private_key = bs58::decode(&self.config.keys.sender_private_key)
-            .into_vec()
-            .map_err(|e| anyhow!("プライベートキーが違うで: {}", e))?;
+    fn check_submit_deadline(&self, deadline: Option<u64>) -> Result<()> {
+        if let Some(deadline) = deadline {
+            let slot = self.client.get_slot()?;
+            if slot >= deadline {
+                return Err(anyhow!(
+                    "Current slot {} has reached the submit deadline {}; not submitting",
+                    slot,
+                    deadline
+                ));
+            }
+        }
+        Ok(())
+    }
 
-        if private_key.len() != 64 {
-            return Err(anyhow!("Invalid private key length"));
+    fn submit_transaction(
+        &self,
+        transaction: &Transaction,
+        amount: u64,
+        deadline: Option<u64>,
+    ) -> Result<Signature> {
+        self.check_submit_deadline(deadline)?;
+        self.explain("プリフライトを省略して送信し、confirmedになるまで確認する");
+        let signature = self.broadcast_transaction(transaction)?;
+        self.explain(format!(
+            "送信した。{}秒以内に確認されなければタイムアウトとする: {}",
+            self.config.transaction.confirmation_timeout, signature
+        ));
+        if let Err(e) = self.save_pending(&signature, transaction, amount) {
+            warn!("送信中の状態を保存できませんでした: {}", e);
         }
 
-        let keypair = Keypair::from_bytes(&private_key)
-            .map_err(|e| anyhow!("Failed to create keypair: {}", e))?;
+        if let Err(e) = self.confirm_signature(&signature) {
+            if !error::is_confirmation_timeout(&e) {
+                return Err(e);
+            }
+            info!("確認がタイムアウトしました。ブロックハッシュが失効するまで着地を待ちます");
+            if !self.landed_before_expiry(transaction)? {
+                self.invalidate_cached_blockhash(&transaction.message.recent_blockhash);
+                self.clear_pending();
+                return Err(TransferError::BlockhashExpired {
+                    signature: signature.to_string(),
+                }
+                .into());
+            }
+            self.confirm_signature(&signature)?;
+        }
+        self.explain("confirmedになった");
+        self.clear_pending();
+
+        Ok(signature)
+    }
+
+    fn send_config(&self) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            skip_preflight: true,
+            preflight_commitment: None,
+            encoding: None,
+            max_retries: None,
+            min_context_slot: self.config.transaction.min_context_slot,
+        }
+    }
 
-        Ok(keypair)
+    fn broadcast_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        self.trace_message(&transaction.message);
+        if self.config.network.broadcast_to_all && !self.config.network.fallback_rpc_urls.is_empty()
+        {
+            return self.broadcast_to_all(transaction);
+        }
+        Ok(self
+            .client
+            .send_transaction_with_config(transaction, self.send_config())?)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.config
+                .transaction
+                .poll_interval_ms
+                .unwrap_or(DEFAULT_POLL_INTERVAL_MS),
+        )
     }
+
+    fn confirm_signature(&self, signature: &Signature) -> Result<()> {
+        let timeout = self.config.transaction.confirmation_timeout;
+        let started = Instant::now();
+
+        loop {
+            let status = match self.client.get_signature_statuses(&[*signature]) {
+                Ok(response) if self.stale_context(response.context.slot) => None,
+                Ok(response) => response.value[0].clone(),
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(status) = status {
+                if let Some(err) = status.err {
+                    return Err(anyhow!("Transaction {} failed: {}", signature, err));
+                }
+                if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                    return Ok(());
+                }
+            }
+
+            if started.elapsed() >= Duration::from_secs(timeout) {
+                return Err(TransferError::ConfirmationTimeout {
+                    signature: signature.to_string(),
+                    timeout,
+                }
+                .into());
+            }
+            // With confirm_by_account a receiver credit ends the wait early.
+            self.wait_for_receiver_credit(self.poll_interval());
+        }
+    }
+
+    fn sender_secret(&self) -> Result<String> {
+        let keys = &self.config.keys;
+        match (&keys.keyring_service, &keys.keyring_user) {
+            (Some(service), Some(user)) => secret_store::load_secret(service, user),
+            (None, None) => keys
+                .sender_private_key
+                .clone()
+                .ok_or_else(|| anyhow!("sender_private_key is not configured")),
+            _ => Err(anyhow!(
+                "keyring_service and keyring_user must be set together"
+            )),
+        }
+    }
+
+    fn create_sender_keypair(&self) -> Result<Box<dyn Signer>> {
+        let keys = &self.config.keys;
+        if keys.ledger {
+            return ledger::ledger_signer(keys.ledger_account, keys.ledger_change)
+                .map_err(|e| TransferError::Config(e.to_string()).into());
+        }
+
+        self.sender_secret()
+            .and_then(|secret| keypair_from_secret(&secret, keys.key_encoding))
+            .map(|keypair| Box::new(keypair) as Box<dyn Signer>)
+            .map_err(|e| TransferError::Config(e.to_string()).into())
+    }
+
+    fn store_key(&self, service: Option<String>, user: Option<String>) -> Result<()> {
+        let service = service
+            .or_else(|| self.config.keys.keyring_service.clone())
+            .ok_or_else(|| anyhow!("Keyring service is not specified"))?;
+        let user = user
+            .or_else(|| self.config.keys.keyring_user.clone())
+            .ok_or_else(|| anyhow!("Keyring user is not specified"))?;
+
+        println!("保存する秘密鍵を入力してください:");
+        let mut secret = String::new();
+        std::io::stdin().lock().read_line(&mut secret)?;
+        let secret = secret.trim();
+
+        let keypair = keypair_from_secret(secret, self.config.keys.key_encoding)?;
+        secret_store::store_secret(&service, &user, secret)?;
+        println!(
+            "キーリングに保存しました ({}/{}): {}",
+            service,
+            user,
+            keypair.pubkey()
+        );
+
+        Ok(())
+    }
+}
+
+fn keypair_from_secret(secret: &str, encoding: KeyEncoding) -> Result<Keypair> {
+    let private_key = match encoding {
+        KeyEncoding::Base58 => bs58::decode(secret)
+            .into_vec()
+            .map_err(|e| anyhow!("プライベートキーが違うで: {}", e))?,
+        KeyEncoding::Base64 => base64::engine::general_purpose::STANDARD
+            .decode(secret)
+            .map_err(|e| anyhow!("プライベートキーが違うで: {}", e))?,
+    };
+
+    if private_key.len() != 64 {
+        return Err(anyhow!("Invalid private key length"));
+    }
+
+    let keypair = Keypair::from_bytes(&private_key)
+        .map_err(|e| anyhow!("Failed to create keypair: {}", e))?;
+
+    Ok(keypair)
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
+async fn main() {
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            use std::io::Write;
+            let label = LOG_LABEL
+                .get()
+                .map(|label| format!(" [{}]", label))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {} {}]{} {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                label,
+                record.args()
+            )
+        })
+        .init();
+
+    if let Err(e) = run() {
+        error!("Error occurred: {}", e);
+        std::process::exit(error::exit_code(&e));
+    }
+}
 
-    let manager = SolanaTransactionManager::new("config/config.toml")?;
+fn generate_config(path: &str) -> Result<()> {
+    let path = Path::new(path);
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, CONFIG_TEMPLATE)?;
+    println!("設定ファイルのひな形を作成しました: {}", path.display());
+
+    Ok(())
+}
+
+// Prefixes deserialization errors with the path of the offending key, e.g.
+// "transaction.amount: expected integer lamports ..., got 'abc'".
+fn describe_config_error(error: serde_path_to_error::Error<config::ConfigError>) -> String {
+    let path = error.path().to_string();
+    let message = match error.into_inner() {
+        config::ConfigError::Type {
+            origin,
+            unexpected,
+            expected,
+            ..
+        } => match origin {
+            Some(origin) => format!("expected {}, got {} (in {})", expected, unexpected, origin),
+            None => format!("expected {}, got {}", expected, unexpected),
+        },
+        other => other.to_string(),
+    };
+
+    if path == "." {
+        message
+    } else {
+        format!("{}: {}", path, message)
+    }
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    format::set_locale(cli.locale);
+    format::set_units(cli.units);
+    if let Some(label) = &cli.label {
+        let _ = LOG_LABEL.set(label.clone());
+    }
+
+    if let Some(Command::GenerateConfig) = cli.command {
+        return generate_config(&cli.config[0]);
+    }
+    let mut manager = match SolanaTransactionManager::new(&cli.config) {
+        Ok(manager) => manager,
+        Err(e) => {
+            if let Some(Command::Doctor) = cli.command {
+                doctor::report_config_failure(&e);
+            }
+            return Err(e);
+        }
+    };
+    if let Some(commitment) = cli.commitment_for_balance {
+        manager.config.network.balance_commitment = Some(commitment);
+    }
+    if cli.label.is_some() {
+        manager.config.transaction.label = cli.label.clone();
+    }
+    if let Some(label) = &manager.config.transaction.label {
+        let _ = LOG_LABEL.set(label.clone());
+    }
+    if cli.explain {
+        manager.config.output.explain = true;
+    }
+    if cli.trace_tx {
+        manager.config.output.trace_tx = true;
+    }
+    if cli.table {
+        manager.config.output.table = true;
+    }
+    if cli.json {
+        manager.config.output.json = true;
+    }
+    if cli.blockhash.is_some() {
+        manager.fixed_blockhash = cli.blockhash;
+    }
+    if cli.output_signature_file.is_some() {
+        manager.config.output.signature_file = cli.output_signature_file.clone();
+    }
+    manager.explain(format!(
+        "設定を読み込んだ: {} (RPC: {})",
+        cli.config.join(", "),
+        manager.config.network.rpc_url
+    ));
+
+    if cli.print_config {
+        println!("{}", serde_json::to_string_pretty(&manager.config)?);
+        return Ok(());
+    }
+
+    let options = SendOptions {
+        force: cli.force,
+        allow_zero: cli.allow_zero,
+        track_finalized: cli.confirm_finalized_after_return,
+        network_check: cli.network_check,
+        skip_duplicate_check: false,
+        yes: cli.yes,
+        yes_large: cli.yes_large,
+        whole_transfer_checked: false,
+    };
+
+    manager.pending_mode = match &cli.command {
+        Some(Command::Batch { .. }) => PendingMode::Batch,
+        Some(Command::Distribute { .. }) => PendingMode::Distribute,
+        Some(Command::Memo { .. }) => PendingMode::Memo,
+        Some(Command::Recurring { .. }) => PendingMode::Recurring,
+        _ if cli.stdin => PendingMode::Stdin,
+        _ => PendingMode::Transfer,
+    };
+    // A crash mid-batch leaves one of its transactions pending; reconcile it before the batch
+    // starts again so a landed transaction is not paid a second time.
+    if matches!(
+        manager.pending_mode,
+        PendingMode::Batch | PendingMode::Distribute | PendingMode::Memo
+    ) {
+        if let Some(signature) = manager.resume_pending(&options)? {
+            manager.record_signature(&signature);
+            if manager.pending_mode == PendingMode::Memo {
+                println!("メモを記録しました: {}", signature);
+                return Ok(());
+            }
+            return Err(anyhow!(
+                "Transaction {} from the previous run landed; remove the recipients it paid before sending again",
+                signature
+            ));
+        }
+    }
+
+    match cli.command {
+        Some(Command::StoreKey { service, user }) => return manager.store_key(service, user),
+        Some(Command::Doctor) => return manager.doctor(&cli.config),
+        Some(Command::FindPayments { reference, since }) => {
+            return manager.find_payments(&reference, since)
+        }
+        Some(Command::Batch {
+            file,
+            concurrent,
+            report,
+        }) => {
+            let result = manager.send_batch(&file, &options, concurrent, report.as_deref());
+            manager.wait_for_background();
+            manager.print_rpc_summary();
+            return result.map(|_| ());
+        }
+        Some(Command::Distribute {
+            total,
+            receivers,
+            derive_base,
+            count,
+            concurrent,
+            report,
+        }) => {
+            let receivers = match (receivers, derive_base) {
+                (Some(path), _) => distribute::Receivers::File(path),
+                (None, Some(base)) => distribute::Receivers::Derived {
+                    base: Pubkey::from_str(&base).map_err(|e| {
+                        TransferError::Config(format!("Invalid derive base: {}", e))
+                    })?,
+                    count: count.unwrap_or_default(),
+                },
+                (None, None) => unreachable!("clap requires --receivers or --derive-base"),
+            };
+            let result =
+                manager.distribute(total, receivers, &options, concurrent, report.as_deref());
+            manager.wait_for_background();
+            manager.print_rpc_summary();
+            return result.map(|_| ());
+        }
+        Some(Command::Memo { text }) => {
+            let signature = manager.send_memo(&text)?;
+            println!("メモを記録しました: {}", signature);
+            return Ok(());
+        }
+        Some(Command::SimulateBatch { file }) => return manager.simulate_batch(&file),
+        Some(Command::Watch {
+            pubkey,
+            until,
+            interval,
+        }) => return manager.watch_balance(&pubkey, until, interval),
+        Some(Command::RequiredFunding { batch, count }) => {
+            return manager.print_required_funding(batch.as_deref(), count)
+        }
+        Some(Command::Recurring {
+            every,
+            count,
+            pause_file,
+        }) => {
+            let result = manager.send_recurring(&options, every, count, &pause_file);
+            manager.wait_for_background();
+            manager.print_rpc_summary();
+            return result;
+        }
+        Some(Command::GenerateConfig) | None => {}
+    }
+
+    if cli.stdin {
+        let result = manager.send_from_stdin(&options);
+        manager.wait_for_background();
+        manager.print_rpc_summary();
+        return result;
+    }
+
+    if let Some(space) = cli.init_account_space {
+        manager.use_rent_exempt_amount(space)?;
+    }
+
+    if cli.dry_run && manager.is_multisig() {
+        return manager
+            .propose_multisig_transfer(&options, true)
+            .map(|_| ());
+    }
+    if cli.dry_run {
+        return manager.dry_run(&options, cli.dry_run_report.as_deref());
+    }
+
+    if cli.simulate || cli.simulate_with_sigverify {
+        return manager.simulate_transfer(simulate::SimulateOptions {
+            sig_verify: cli.simulate_with_sigverify,
+            replace_recent_blockhash: !cli.keep_blockhash,
+        });
+    }
+
+    if let Some(signature) = manager.resume_pending(&options)? {
+        manager.record_signature(&signature);
+        println!("TX成功!: {}", signature);
+        return Ok(());
+    }
+
+    if manager.is_multisig() {
+        if let Some((signature, index)) = manager.propose_multisig_transfer(&options, false)? {
+            println!(
+                "マルチシグ提案 #{} を作成して承認しました: {}",
+                index, signature
+            );
+        }
+        return Ok(());
+    }
 
     let sender_keypair = manager.create_sender_keypair()?;
     println!("送信アドレス: {}", sender_keypair.pubkey());
-    println!("受取アドレス: {}", manager.config.keys.receiver_public_key);
+    println!("受取アドレス: {}", manager.receiver_pubkey()?);
 
     let current_balance = manager.get_balance(&sender_keypair.pubkey())?;
-    println!(
-        "現在の残高: {} SOL",
-        (current_balance as f64) / 1_000_000_000.0
-    );
+    println!("現在の残高: {}", format_sol(current_balance));
 
-    match manager.send_transaction() {
-        Ok(signature) => {
-            println!("TX成功!: {}", signature);
-        }
+    if let Some(parts) = manager
+        .config
+        .transaction
+        .split_into
+        .filter(|parts| *parts > 1)
+    {
+        let result = manager.send_split(&options, parts);
+        manager.wait_for_background();
+        manager.print_rpc_summary();
+        return result.map(|_| ());
+    }
+
+    let outcome = match manager.send_transaction(&options) {
+        Ok(outcome) => outcome,
         Err(e) => {
-            error!("Error occurred: {}", e);
+            manager.notify_failure(&e);
+            manager.wait_for_background();
+            manager.print_rpc_summary();
+            return Err(e);
         }
-    }
+    };
+    manager.notify_success(&outcome);
+    println!("{}", manager.success_message(&outcome));
+    manager.wait_for_background();
+    manager.print_rpc_summary();
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use spl_associated_token_account::{
+        get_associated_token_address, instruction::create_associated_token_account_idempotent,
+    };
+
+    pub(crate) const RECEIVER: &str = "DKy6SpGtVjGigwtQL84XkY95VkQz2af4R4UUvzpr17E5";
+    pub(crate) const MINT: &str = "So11111111111111111111111111111111111111112";
+    const REFERENCE: &str = "11111111111111111111111111111112";
+
+    pub(crate) fn manager(transaction: &str, extra: &str) -> SolanaTransactionManager {
+        let toml = format!(
+            "[network]\nrpc_url = \"http://127.0.0.1:8899\"\n\
+             [keys]\nreceiver_public_key = \"{}\"\n\
+             [transaction]\namount = 1\nmin_balance = 0\nconfirmation_timeout = 60\n{}\n{}",
+            RECEIVER, transaction, extra
+        );
+        let settings = Config::builder()
+            .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        SolanaTransactionManager::with_settings(settings.try_deserialize().unwrap()).unwrap()
+    }
+
+    fn serialized(message: &Message) -> Vec<u8> {
+        bincode::serialize(message).unwrap()
+    }
+
+    fn sender() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    fn blockhash() -> Hash {
+        Hash::new_from_array([3; 32])
+    }
+
+    #[test]
+    fn sol_transfer_message_matches_golden() {
+        let manager = manager("compute_unit_limit = 200000", "");
+        let inputs = TransferInputs {
+            priority_fee: Some(5000),
+            decimals: None,
+        };
+        let message = manager
+            .build_transfer_message(&sender(), 1_000, &inputs, blockhash())
+            .unwrap();
+
+        let receiver = Pubkey::from_str(RECEIVER).unwrap();
+        let expected = Message::new_with_blockhash(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                ComputeBudgetInstruction::set_compute_unit_price(5000),
+                system_instruction::transfer(&sender(), &receiver, 1_000),
+            ],
+            Some(&sender()),
+            &blockhash(),
+        );
+        assert_eq!(serialized(&message), serialized(&expected));
+    }
+
+    #[test]
+    fn zero_priority_fee_adds_no_instruction() {
+        let manager = manager("", "");
+        let inputs = TransferInputs {
+            priority_fee: Some(0),
+            decimals: None,
+        };
+        let instructions = manager
+            .build_transfer_instructions(&sender(), 1, &inputs)
+            .unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            instructions[0].program_id,
+            solana_program::system_program::id()
+        );
+    }
+
+    #[test]
+    fn reference_is_appended_to_the_transfer() {
+        let manager = manager(&format!("reference = \"{}\"", REFERENCE), "");
+        let instructions = manager
+            .build_transfer_instructions(&sender(), 1, &TransferInputs::default())
+            .unwrap();
+        let transfer = instructions.last().unwrap();
+        assert_eq!(
+            transfer.accounts.last(),
+            Some(&AccountMeta::new_readonly(
+                Pubkey::from_str(REFERENCE).unwrap(),
+                false
+            ))
+        );
+    }
+
+    #[test]
+    fn token_transfer_message_matches_golden() {
+        let manager = manager("", &format!("[token]\nmint = \"{}\"", MINT));
+        let inputs = TransferInputs {
+            priority_fee: None,
+            decimals: Some(9),
+        };
+        let message = manager
+            .build_transfer_message(&sender(), 42, &inputs, blockhash())
+            .unwrap();
+
+        let receiver = Pubkey::from_str(RECEIVER).unwrap();
+        let mint = Pubkey::from_str(MINT).unwrap();
+        let expected = Message::new_with_blockhash(
+            &[
+                create_associated_token_account_idempotent(
+                    &sender(),
+                    &receiver,
+                    &mint,
+                    &spl_token::id(),
+                ),
+                spl_token::instruction::transfer_checked(
+                    &spl_token::id(),
+                    &get_associated_token_address(&sender(), &mint),
+                    &mint,
+                    &get_associated_token_address(&receiver, &mint),
+                    &sender(),
+                    &[],
+                    42,
+                    9,
+                )
+                .unwrap(),
+            ],
+            Some(&sender()),
+            &blockhash(),
+        );
+        assert_eq!(serialized(&message), serialized(&expected));
+    }
+
+    #[test]
+    fn token_transfer_requires_decimals() {
+        let manager = manager("", &format!("[token]\nmint = \"{}\"", MINT));
+        assert!(manager
+            .build_transfer_instructions(&sender(), 42, &TransferInputs::default())
+            .is_err());
+    }
+
+    #[test]
+    fn success_template_fills_every_field_in_the_selected_units() {
+        let manager = manager(
+            "",
+            "[output]\nsuccess_template = \"{sender} {receiver} {amount} {signature} {fee} {balance_before} {balance_after}\"",
+        );
+        let outcome = TransferOutcome {
+            signature: Signature::default(),
+            summary: TransferSummary {
+                sender: "s".to_string(),
+                receiver: "r".to_string(),
+                amount: 1_500_000_000,
+                mint: None,
+                fee: 5_000,
+                label: None,
+            },
+            balance_before: 2_000_000_000,
+            balance_after: 499_995_000,
+        };
+        let signature = Signature::default().to_string();
+
+        assert_eq!(
+            manager.render_success(&outcome, format::Units::Sol),
+            format!("s r 1.5 SOL {} 0.000005 SOL 2 SOL 0.499995 SOL", signature)
+        );
+        assert_eq!(
+            manager.render_success(&outcome, format::Units::Lamports),
+            format!(
+                "s r 1500000000 lamports {} 5000 lamports 2000000000 lamports 499995000 lamports",
+                signature
+            )
+        );
+    }
+}
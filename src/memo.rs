@@ -0,0 +1,31 @@
+use crate::{error::TransferError, SolanaTransactionManager};
+use anyhow::Result;
+use solana_sdk::signature::Signature;
+
+impl SolanaTransactionManager {
+    pub fn send_memo(&self, text: &str) -> Result<Signature> {
+        if text.is_empty() {
+            return Err(TransferError::Config("Memo text is empty".to_string()).into());
+        }
+
+        let deadline = self.submit_deadline()?;
+        let sender_keypair = self.create_sender_keypair()?;
+        let sender = sender_keypair.pubkey();
+
+        let mut instructions = self.compute_budget_instructions(self.priority_fee(&[sender])?)?;
+        instructions.push(spl_memo::build_memo(text.as_bytes(), &[&sender]));
+
+        let transaction = self.sign_transaction(&instructions, sender_keypair.as_ref())?;
+        let fee = self.client.get_fee_for_message(&transaction.message)?;
+        let balance = self.get_balance(&sender)?;
+        if balance < fee {
+            return Err(TransferError::InsufficientBalance {
+                balance,
+                required: fee,
+            }
+            .into());
+        }
+
+        self.submit_transaction(&transaction, 0, deadline)
+    }
+}
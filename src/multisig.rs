@@ -0,0 +1,331 @@
+use crate::{
+    error::TransferError, format::format_sol, SendOptions, SolanaTransactionManager, TransferCost,
+    TransferSummary,
+};
+use anyhow::{anyhow, Result};
+use log::info;
+use solana_program::{hash::hash, system_program};
+use solana_sdk::{
+    compute_budget,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use std::str::FromStr;
+
+// Squads Protocol v4 is the only multisig program supported. Other programs (Squads v3,
+// SPL Token multisig, Realms) use different account layouts and are rejected at config time.
+const SQUADS_V4_PROGRAM_ID: &str = "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf";
+// discriminator + create_key + config_authority + threshold (u16) + time_lock (u32)
+const TRANSACTION_INDEX_OFFSET: usize = 8 + 32 + 32 + 2 + 4;
+
+#[derive(Debug, Clone, Copy, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MultisigProgram {
+    #[default]
+    SquadsV4,
+}
+
+impl MultisigProgram {
+    fn program_id(self) -> Pubkey {
+        match self {
+            MultisigProgram::SquadsV4 => Pubkey::from_str(SQUADS_V4_PROGRAM_ID).unwrap(),
+        }
+    }
+}
+
+fn discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+fn pda(program_id: &Pubkey, seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+// Squads' compact TransactionMessage: u8-prefixed account keys and instructions,
+// u16-prefixed instruction data, and no address lookup tables.
+fn vault_transaction_message(vault: &Pubkey, instructions: &[Instruction]) -> Result<Vec<u8>> {
+    let message = Message::new(instructions, Some(vault));
+    let header = message.header;
+    let num_signers = header.num_required_signatures;
+    let num_keys = message.account_keys.len();
+    let compact_len = |len: usize| {
+        u8::try_from(len).map_err(|_| anyhow!("Multisig transaction has too many entries"))
+    };
+
+    let mut data = vec![
+        num_signers,
+        num_signers - header.num_readonly_signed_accounts,
+        (num_keys - num_signers as usize - header.num_readonly_unsigned_accounts as usize) as u8,
+        compact_len(num_keys)?,
+    ];
+    for key in &message.account_keys {
+        data.extend_from_slice(key.as_ref());
+    }
+
+    data.push(compact_len(message.instructions.len())?);
+    for instruction in &message.instructions {
+        data.push(instruction.program_id_index);
+        data.push(compact_len(instruction.accounts.len())?);
+        data.extend_from_slice(&instruction.accounts);
+        let data_len = u16::try_from(instruction.data.len())
+            .map_err(|_| anyhow!("Multisig instruction data is too large"))?;
+        data.extend_from_slice(&data_len.to_le_bytes());
+        data.extend_from_slice(&instruction.data);
+    }
+    data.push(0);
+
+    Ok(data)
+}
+
+impl SolanaTransactionManager {
+    fn multisig_address(&self) -> Result<Option<(Pubkey, MultisigProgram, u8)>> {
+        self.config
+            .multisig
+            .as_ref()
+            .map(|multisig| {
+                let address = Pubkey::from_str(&multisig.address).map_err(|e| {
+                    TransferError::Config(format!("Invalid multisig address: {}", e))
+                })?;
+                Ok((address, multisig.program, multisig.vault_index))
+            })
+            .transpose()
+    }
+
+    pub(crate) fn is_multisig(&self) -> bool {
+        self.config.multisig.is_some()
+    }
+
+    // Creates a vault transaction holding the transfer, opens a proposal for it and approves
+    // it as the configured member, all in one transaction. Execution is left to the multisig
+    // once the approval threshold is reached. A dry run simulates the proposal instead and
+    // returns None.
+    pub fn propose_multisig_transfer(
+        &self,
+        options: &SendOptions,
+        dry_run: bool,
+    ) -> Result<Option<(Signature, u64)>> {
+        let (multisig, program, vault_index) = self
+            .multisig_address()?
+            .ok_or_else(|| TransferError::Config("[multisig] is not configured".to_string()))?;
+        let program_id = program.program_id();
+        let member = self.create_sender_keypair()?;
+        let amount = self.amount()?;
+        let receiver = self.receiver_pubkey()?;
+        self.check_receiver_allowed(&receiver)?;
+        if amount == 0 && !options.allow_zero {
+            return Err(TransferError::Config(
+                "amount is 0; pass --allow-zero to send a zero-amount transaction anyway"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let vault = pda(
+            &program_id,
+            &[b"multisig", multisig.as_ref(), b"vault", &[vault_index]],
+        );
+        let account = self
+            .client
+            .get_account(&multisig)
+            .map_err(|e| anyhow!("Multisig account {} not found: {}", multisig, e))?;
+        if account.owner != program_id {
+            return Err(anyhow!(
+                "Multisig account {} is owned by {}, expected {}",
+                multisig,
+                account.owner,
+                program_id
+            ));
+        }
+        let index_bytes = account
+            .data
+            .get(TRANSACTION_INDEX_OFFSET..TRANSACTION_INDEX_OFFSET + 8)
+            .ok_or_else(|| anyhow!("Failed to decode multisig account {}", multisig))?;
+        let transaction_index = u64::from_le_bytes(index_bytes.try_into()?) + 1;
+
+        let mint = self.token_mint()?;
+        if mint.is_none() {
+            let vault_balance = self.get_balance(&vault)?;
+            if vault_balance < amount {
+                return Err(TransferError::InsufficientBalance {
+                    balance: vault_balance,
+                    required: amount,
+                }
+                .into());
+            }
+        }
+        info!(
+            "マルチシグ {} のvault {} から送金を提案します ({})",
+            multisig,
+            vault,
+            format_sol(amount)
+        );
+
+        self.wait_for_fee_market(&[vault, receiver])?;
+        let inner: Vec<Instruction> = self
+            .build_transfer_instructions(&vault, amount, &self.transfer_inputs(&vault)?)?
+            .into_iter()
+            .filter(|instruction| instruction.program_id != compute_budget::id())
+            .collect();
+        let transaction_message = vault_transaction_message(&vault, &inner)?;
+
+        let index = transaction_index.to_le_bytes();
+        let transaction = pda(
+            &program_id,
+            &[b"multisig", multisig.as_ref(), b"transaction", &index],
+        );
+        let proposal = pda(
+            &program_id,
+            &[
+                b"multisig",
+                multisig.as_ref(),
+                b"transaction",
+                &index,
+                b"proposal",
+            ],
+        );
+
+        let mut create_data = discriminator("vault_transaction_create").to_vec();
+        create_data.push(vault_index);
+        create_data.push(0);
+        create_data.extend_from_slice(&(transaction_message.len() as u32).to_le_bytes());
+        create_data.extend_from_slice(&transaction_message);
+        create_data.push(0);
+
+        let mut proposal_data = discriminator("proposal_create").to_vec();
+        proposal_data.extend_from_slice(&index);
+        proposal_data.push(0);
+
+        let mut approve_data = discriminator("proposal_approve").to_vec();
+        approve_data.push(0);
+
+        let member_pubkey = member.pubkey();
+        let instructions = [
+            Instruction::new_with_bytes(
+                program_id,
+                &create_data,
+                vec![
+                    AccountMeta::new(multisig, false),
+                    AccountMeta::new(transaction, false),
+                    AccountMeta::new_readonly(member_pubkey, true),
+                    AccountMeta::new(member_pubkey, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                program_id,
+                &proposal_data,
+                vec![
+                    AccountMeta::new_readonly(multisig, false),
+                    AccountMeta::new(proposal, false),
+                    AccountMeta::new_readonly(member_pubkey, true),
+                    AccountMeta::new(member_pubkey, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                program_id,
+                &approve_data,
+                vec![
+                    AccountMeta::new_readonly(multisig, false),
+                    AccountMeta::new(member_pubkey, true),
+                    AccountMeta::new(proposal, false),
+                ],
+            ),
+        ];
+
+        let fee = self
+            .client
+            .get_fee_for_message(&Message::new_with_blockhash(
+                &instructions,
+                Some(&member_pubkey),
+                &self.client.get_latest_blockhash()?,
+            ))?;
+        let cost = TransferCost {
+            amount: if mint.is_some() { 0 } else { amount },
+            fee,
+            account_rent: match mint {
+                Some(mint) => self.token_account_rent(&receiver, &mint)?,
+                None => 0,
+            },
+        };
+
+        if dry_run {
+            if mint.is_none() {
+                self.check_dust(cost.amount, cost.fee, options)?;
+            }
+            self.check_total_cost(&cost)?;
+            let signed = self.sign_transaction(&instructions, member.as_ref())?;
+            self.trace_message(&signed.message);
+            let result = self.client.simulate_transaction(&signed)?.value;
+            for log in result.logs.unwrap_or_default() {
+                println!("  {}", log);
+            }
+            if let Some(err) = result.err {
+                return Err(anyhow!("Simulation failed: {}", err));
+            }
+            println!(
+                "ドライラン: マルチシグ提案 #{} のシミュレーションに成功しました (送信はしていません)",
+                transaction_index
+            );
+            return Ok(None);
+        }
+
+        let summary = TransferSummary {
+            sender: vault.to_string(),
+            receiver: receiver.to_string(),
+            amount,
+            mint: mint.map(|mint| mint.to_string()),
+            fee,
+            label: self.config.transaction.label.clone(),
+        };
+        self.preflight(&vault, &receiver, mint.as_ref(), &summary, &cost, options)?;
+
+        let signed = self.sign_transaction(&instructions, member.as_ref())?;
+        let deadline = self.submit_deadline()?;
+        let signature = self.submit_transaction(&signed, amount, deadline)?;
+
+        Ok(Some((signature, transaction_index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::system_instruction;
+
+    #[test]
+    fn vault_transaction_message_uses_squads_compact_layout() {
+        let vault = Pubkey::new_from_array([1; 32]);
+        let receiver = Pubkey::new_from_array([2; 32]);
+        let transfer = system_instruction::transfer(&vault, &receiver, 42);
+
+        let mut expected = vec![1, 1, 1, 3];
+        expected.extend_from_slice(vault.as_ref());
+        expected.extend_from_slice(receiver.as_ref());
+        expected.extend_from_slice(system_program::id().as_ref());
+        expected.extend_from_slice(&[1, 2, 2, 0, 1]);
+        expected.extend_from_slice(&(transfer.data.len() as u16).to_le_bytes());
+        expected.extend_from_slice(&transfer.data);
+        expected.push(0);
+
+        assert_eq!(
+            vault_transaction_message(&vault, &[transfer]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn vault_transaction_message_rejects_oversized_instruction_data() {
+        let vault = Pubkey::new_from_array([1; 32]);
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_from_array([3; 32]),
+            &vec![0; u16::MAX as usize + 1],
+            vec![AccountMeta::new(vault, true)],
+        );
+        assert!(vault_transaction_message(&vault, &[instruction]).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    format::format_sol, webhook, SolanaTransactionManager, TransferOutcome, TransferSummary,
+};
+use log::{info, warn};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    Success,
+    Failure,
+    LowBalance,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct Notification<'a> {
+    event: NotifyEvent,
+    signature: Option<String>,
+    error: Option<String>,
+    receiver: &'a str,
+    amount: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a TransferSummary>,
+    balance_before: Option<u64>,
+    balance_after: Option<u64>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct LowBalanceNotification {
+    event: NotifyEvent,
+    sender: String,
+    balance: u64,
+    threshold: u64,
+}
+
+pub fn default_events() -> Vec<NotifyEvent> {
+    vec![NotifyEvent::Success, NotifyEvent::Failure]
+}
+
+impl SolanaTransactionManager {
+    fn notify<T: Serialize>(&self, event: NotifyEvent, body: &T) {
+        let Some(notify) = &self.config.notify else {
+            return;
+        };
+        if !notify.events.contains(&event) {
+            return;
+        }
+
+        let body = match serde_json::to_value(body) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("通知を作成できませんでした: {}", e);
+                return;
+            }
+        };
+        let url = notify.webhook_url.clone();
+        let timeout = Duration::from_secs(notify.timeout.unwrap_or(5));
+
+        // Sent in the background so a slow hook does not hold up the next transfer; run()
+        // waits for it before the process exits.
+        let handle = tokio::runtime::Handle::current().spawn(async move {
+            match webhook::post_json_async(&url, body, timeout).await {
+                Ok(status) if status.is_success() => info!("通知を送信しました: {}", status),
+                Ok(status) => warn!("通知先がエラーを返しました: {}", status),
+                Err(e) => warn!("通知の送信に失敗しました: {}", e),
+            }
+        });
+        self.background.lock().unwrap().push(handle);
+    }
+
+    pub(crate) fn check_low_balance(&self, sender: &Pubkey, balance: u64) {
+        let Some(threshold) = self.config.transaction.low_balance_threshold else {
+            return;
+        };
+        if balance >= threshold {
+            return;
+        }
+
+        warn!(
+            "送信元 {} の残高 {} がlow_balance_threshold {} を下回っています。補充してください",
+            sender,
+            format_sol(balance),
+            format_sol(threshold)
+        );
+        self.notify(
+            NotifyEvent::LowBalance,
+            &LowBalanceNotification {
+                event: NotifyEvent::LowBalance,
+                sender: sender.to_string(),
+                balance,
+                threshold,
+            },
+        );
+    }
+
+    pub fn notify_success(&self, outcome: &TransferOutcome) {
+        self.notify(
+            NotifyEvent::Success,
+            &Notification {
+                event: NotifyEvent::Success,
+                signature: Some(outcome.signature.to_string()),
+                error: None,
+                receiver: &outcome.summary.receiver,
+                amount: Some(outcome.summary.amount),
+                summary: Some(&outcome.summary),
+                balance_before: Some(outcome.balance_before),
+                balance_after: Some(outcome.balance_after),
+            },
+        );
+    }
+
+    pub fn notify_failure(&self, error: &anyhow::Error) {
+        let receiver = self
+            .receiver_pubkey()
+            .map(|receiver| receiver.to_string())
+            .unwrap_or_default();
+        self.notify(
+            NotifyEvent::Failure,
+            &Notification {
+                event: NotifyEvent::Failure,
+                signature: None,
+                error: Some(error.to_string()),
+                receiver: &receiver,
+                amount: self.amount().ok(),
+                summary: None,
+                balance_before: None,
+                balance_after: None,
+            },
+        );
+    }
+}
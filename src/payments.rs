@@ -0,0 +1,192 @@
+use crate::{error::TransferError, format::format_sol, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+    transaction::TransactionError,
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, TransactionConfirmationStatus, UiTransactionEncoding,
+    UiTransactionTokenBalance,
+};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Since {
+    Slot(u64),
+    Time(i64),
+}
+
+impl Since {
+    fn includes(&self, info: &RpcConfirmedTransactionStatusWithSignature) -> bool {
+        match self {
+            Since::Slot(slot) => info.slot >= *slot,
+            Since::Time(time) => info
+                .block_time
+                .is_some_and(|block_time| block_time >= *time),
+        }
+    }
+}
+
+pub fn parse_since(value: &str) -> Result<Since, String> {
+    if let Ok(slot) = value.parse::<u64>() {
+        return Ok(Since::Slot(slot));
+    }
+
+    let timestamp = if value.len() == 10 {
+        format!("{}T00:00:00Z", value)
+    } else {
+        value.to_string()
+    };
+    let time = humantime::parse_rfc3339_weak(&timestamp)
+        .map_err(|e| format!("expected a slot or ISO 8601 time: {}", e))?;
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    Ok(Since::Time(seconds as i64))
+}
+
+pub fn status_label(
+    err: Option<&TransactionError>,
+    confirmation_status: Option<&TransactionConfirmationStatus>,
+) -> String {
+    if let Some(err) = err {
+        return format!("failed ({})", err);
+    }
+
+    match confirmation_status {
+        Some(TransactionConfirmationStatus::Processed) => "processed".to_string(),
+        Some(TransactionConfirmationStatus::Confirmed) => "confirmed".to_string(),
+        Some(TransactionConfirmationStatus::Finalized) => "finalized".to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+impl SolanaTransactionManager {
+    fn signatures_since(
+        &self,
+        address: &Pubkey,
+        since: Option<Since>,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        let Some(since) = since else {
+            return Ok(self.client.get_signatures_for_address(address)?);
+        };
+
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self.client.get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    ..Default::default()
+                },
+            )?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            before = Some(
+                Signature::from_str(&last.signature)
+                    .map_err(|e| anyhow!("Invalid signature {}: {}", last.signature, e))?,
+            );
+
+            let page_len = page.len();
+            let kept = signatures.len();
+            signatures.extend(page.into_iter().take_while(|info| since.includes(info)));
+            if signatures.len() - kept < page_len {
+                break;
+            }
+        }
+
+        Ok(signatures)
+    }
+
+    pub fn find_payments(&self, reference: &str, since: Option<Since>) -> Result<()> {
+        let reference = Pubkey::from_str(reference)
+            .map_err(|e| TransferError::Config(format!("Invalid reference pubkey: {}", e)))?;
+        let receiver = self.receiver_pubkey()?;
+        let mint = self.token_mint()?;
+
+        let signatures = self.signatures_since(&reference, since)?;
+        if signatures.is_empty() {
+            println!("該当する支払いはありません: {}", reference);
+            return Ok(());
+        }
+
+        for info in signatures {
+            let signature = Signature::from_str(&info.signature)
+                .map_err(|e| anyhow!("Invalid signature {}: {}", info.signature, e))?;
+            let status = status_label(info.err.as_ref(), info.confirmation_status.as_ref());
+            let received = self.received_amount(&signature, &receiver, mint.as_ref())?;
+
+            match (received, mint) {
+                (Some(amount), Some(_)) => {
+                    println!("{} {} 受取額: {}", signature, status, amount)
+                }
+                (Some(amount), None) => println!(
+                    "{} {} 受取額: {}{}",
+                    signature,
+                    status,
+                    if amount < 0 { "-" } else { "" },
+                    format_sol(amount.unsigned_abs() as u64)
+                ),
+                (None, _) => println!("{} {} 受取額: 不明", signature, status),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn received_amount(
+        &self,
+        signature: &Signature,
+        receiver: &Pubkey,
+        mint: Option<&Pubkey>,
+    ) -> Result<Option<i128>> {
+        let transaction = self.client.get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+
+        let Some(meta) = transaction.transaction.meta else {
+            return Ok(None);
+        };
+
+        if let Some(mint) = mint {
+            let balance_of = |balances: OptionSerializer<Vec<UiTransactionTokenBalance>>| {
+                Option::<Vec<_>>::from(balances)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|balance| {
+                        balance.mint == mint.to_string()
+                            && Option::from(balance.owner.clone()) == Some(receiver.to_string())
+                    })
+                    .filter_map(|balance| balance.ui_token_amount.amount.parse::<i128>().ok())
+                    .sum::<i128>()
+            };
+            let pre = balance_of(meta.pre_token_balances);
+            let post = balance_of(meta.post_token_balances);
+            return Ok(Some(post - pre));
+        }
+
+        let Some(decoded) = transaction.transaction.transaction.decode() else {
+            return Ok(None);
+        };
+        let index = decoded
+            .message
+            .static_account_keys()
+            .iter()
+            .position(|key| key == receiver);
+
+        Ok(index.map(|index| meta.post_balances[index] as i128 - meta.pre_balances[index] as i128))
+    }
+}
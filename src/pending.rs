@@ -0,0 +1,177 @@
+use crate::{
+    error::is_confirmation_timeout, format::format_sol, SendOptions, SolanaTransactionManager,
+};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use solana_sdk::{hash::Hash, signature::Signature, transaction::Transaction};
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
+use std::str::FromStr;
+
+// The mode that submitted a pending transaction. Only a rerun of the same mode treats the
+// reconciled transaction as its own; an entry left by another mode is confirmed and cleared.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, serde_derive::Deserialize, serde_derive::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PendingMode {
+    #[default]
+    Transfer,
+    Batch,
+    Distribute,
+    Memo,
+    Stdin,
+    Recurring,
+}
+
+impl PendingMode {
+    fn label(self) -> &'static str {
+        match self {
+            PendingMode::Transfer => "送金",
+            PendingMode::Batch => "バッチ送金",
+            PendingMode::Distribute => "配布",
+            PendingMode::Memo => "メモ",
+            PendingMode::Stdin => "標準入力からの送金",
+            PendingMode::Recurring => "定期送金",
+        }
+    }
+}
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct PendingTransfer {
+    signature: String,
+    blockhash: String,
+    #[serde(default)]
+    last_valid_block_height: Option<u64>,
+    amount: u64,
+    #[serde(default)]
+    mint: Option<String>,
+    #[serde(default)]
+    mode: PendingMode,
+}
+
+impl PendingTransfer {
+    fn amount_label(&self) -> String {
+        match &self.mint {
+            Some(mint) => format!("トークン {} を {} (最小単位)", mint, self.amount),
+            None => format_sol(self.amount),
+        }
+    }
+}
+
+// Reads the prompt answer from the terminal even when stdin is piped (--stdin), so a data line
+// is never taken as the answer.
+fn read_answer(answer: &mut String) -> Result<()> {
+    if std::io::stdin().is_terminal() {
+        std::io::stdin().lock().read_line(answer)?;
+        return Ok(());
+    }
+    let tty = std::fs::File::open("/dev/tty").map_err(|_| {
+        anyhow!("A pending transfer needs confirmation but there is no terminal; pass --yes to confirm it first")
+    })?;
+    std::io::BufReader::new(tty).read_line(answer)?;
+    Ok(())
+}
+
+impl SolanaTransactionManager {
+    fn pending_path(&self) -> &Path {
+        Path::new(
+            self.config
+                .transaction
+                .pending_state_file
+                .as_deref()
+                .unwrap_or(".pending-transfer.json"),
+        )
+    }
+
+    pub(crate) fn save_pending(
+        &self,
+        signature: &Signature,
+        transaction: &Transaction,
+        amount: u64,
+    ) -> Result<()> {
+        let pending = PendingTransfer {
+            signature: signature.to_string(),
+            blockhash: transaction.message.recent_blockhash.to_string(),
+            last_valid_block_height: self
+                .last_valid_block_height(&transaction.message.recent_blockhash),
+            amount,
+            mint: self.token_mint()?.map(|mint| mint.to_string()),
+            mode: self.pending_mode,
+        };
+        std::fs::write(self.pending_path(), serde_json::to_string(&pending)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn clear_pending(&self) {
+        if let Err(e) = std::fs::remove_file(self.pending_path()) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("送信中の状態ファイルを削除できませんでした: {}", e);
+            }
+        }
+    }
+
+    // Returns the signature only when the pending transaction was submitted by the current mode
+    // and landed; one left by another mode is still confirmed and cleared, but reported here.
+    pub fn resume_pending(&self, options: &SendOptions) -> Result<Option<Signature>> {
+        let path = self.pending_path();
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        let pending: PendingTransfer = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid pending state file {}: {}", path.display(), e))?;
+        let signature = Signature::from_str(&pending.signature)
+            .map_err(|e| anyhow!("Invalid signature in {}: {}", path.display(), e))?;
+
+        println!(
+            "前回の{}で送信して未確認のTXがあります: {} ({})",
+            pending.mode.label(),
+            signature,
+            pending.amount_label()
+        );
+        let mut answer = String::new();
+        if !options.yes {
+            println!("先にこのTXの確認を行いますか? [Y/n]");
+            read_answer(&mut answer)?;
+        }
+        if answer.trim().eq_ignore_ascii_case("n") {
+            self.clear_pending();
+            return Ok(None);
+        }
+
+        let response = self.client.get_signature_statuses(&[signature])?;
+        let status = if self.stale_context(response.context.slot) {
+            None
+        } else {
+            response.value[0].clone()
+        };
+        let blockhash = Hash::from_str(&pending.blockhash)
+            .map_err(|e| anyhow!("Invalid blockhash in {}: {}", path.display(), e))?;
+        if let Some(height) = pending.last_valid_block_height {
+            self.remember_blockhash(blockhash, height);
+        }
+        if status.is_none() && self.blockhash_expired(&blockhash)? {
+            info!(
+                "前回のTXはブロックハッシュが失効しており着地していません: {}",
+                signature
+            );
+            self.clear_pending();
+            return Ok(None);
+        }
+
+        let result = self.confirm_signature(&signature);
+        if !result.as_ref().is_err_and(is_confirmation_timeout) {
+            self.clear_pending();
+        }
+        result?;
+        if pending.mode != self.pending_mode {
+            info!(
+                "前回の{}のTXを確認しました: {}",
+                pending.mode.label(),
+                signature
+            );
+            return Ok(None);
+        }
+        Ok(Some(signature))
+    }
+}
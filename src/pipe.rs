@@ -0,0 +1,116 @@
+use crate::{
+    amount::{Amount, Rounding},
+    error::TransferError,
+    SendOptions, SolanaTransactionManager,
+};
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use solana_sdk::pubkey::Pubkey;
+use std::io::BufRead;
+use std::str::FromStr;
+
+// The amount is read like the amount setting, so a token transfer still rejects a SOL-style
+// decimal instead of sending it as base units.
+fn parse_line(line: &str) -> Result<(Pubkey, Amount)> {
+    let (receiver, amount) = line
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("expected '<receiver> <amount>'"))?;
+    let receiver =
+        Pubkey::from_str(receiver).map_err(|e| anyhow!("invalid receiver {}: {}", receiver, e))?;
+    let amount: Amount = amount.trim().parse()?;
+    if !matches!(amount, Amount::File(_)) && amount.resolve(Rounding::Ceil)? == 0 {
+        return Err(anyhow!("amount is 0"));
+    }
+    Ok((receiver, amount))
+}
+
+impl SolanaTransactionManager {
+    // Reads "<receiver> <amount>" lines and sends each one, printing only the signature on
+    // stdout so the output can be piped on. Bad lines and failed sends are reported and skipped.
+    pub fn send_from_stdin(&mut self, options: &SendOptions) -> Result<()> {
+        if let Some(signature) = self.resume_pending(options)? {
+            info!("前回の送金を確認しました: {}", signature);
+        }
+
+        let mut total = 0;
+        let mut failed = 0;
+        for (index, line) in std::io::stdin().lock().lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            total += 1;
+
+            let result = parse_line(line).and_then(|(receiver, amount)| {
+                self.config.keys.receiver_public_key = Some(receiver.to_string());
+                self.config.keys.receiver_keypair_path = None;
+                self.config.transaction.amount = amount;
+                self.send_transaction(options)
+            });
+            match result {
+                Ok(outcome) => {
+                    self.notify_success(&outcome);
+                    println!("{}", outcome.signature);
+                }
+                Err(e) => {
+                    error!("{}行目: {}", index + 1, e);
+                    self.notify_failure(&e);
+                    failed += 1;
+                }
+            }
+        }
+
+        if failed > 0 {
+            return Err(TransferError::PartialBatchFailure { failed, total }.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manager, MINT, RECEIVER};
+    use spl_token::state::Mint;
+
+    #[test]
+    fn parse_line_reads_the_amount_like_the_config() {
+        let (receiver, amount) = parse_line(&format!("{} 1500", RECEIVER)).unwrap();
+        assert_eq!(receiver.to_string(), RECEIVER);
+        assert!(matches!(amount, Amount::Lamports(1500)));
+
+        let (_, amount) = parse_line(&format!("{}  0.5 SOL", RECEIVER)).unwrap();
+        assert!(matches!(amount, Amount::Sol(ref text) if text == "0.5 SOL"));
+
+        assert!(parse_line(RECEIVER).is_err());
+        assert!(parse_line(&format!("{} 0.5", RECEIVER)).is_err());
+        assert!(parse_line("not-a-pubkey 1").is_err());
+        assert!(parse_line(&format!("{} 0", RECEIVER)).is_err());
+        assert!(parse_line(&format!("{} 0 SOL", RECEIVER)).is_err());
+    }
+
+    #[test]
+    fn sol_amount_line_is_rejected_for_a_token_transfer() {
+        let mut manager = manager("", &format!("[token]\nmint = \"{}\"", MINT));
+        let mint = Pubkey::from_str(MINT).unwrap();
+        manager.mints.lock().unwrap().insert(
+            mint,
+            Mint {
+                supply: u64::MAX,
+                decimals: 9,
+                is_initialized: true,
+                ..Mint::default()
+            },
+        );
+
+        let (_, amount) = parse_line(&format!("{} 0.5 SOL", RECEIVER)).unwrap();
+        manager.config.transaction.amount = amount;
+        let lamports = manager.amount().unwrap();
+        assert!(manager.check_token_amount(&mint, lamports).is_err());
+
+        let (_, amount) = parse_line(&format!("{} 500", RECEIVER)).unwrap();
+        manager.config.transaction.amount = amount;
+        assert!(manager.check_token_amount(&mint, 500).is_ok());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{SolanaTransactionManager, TransferSummary};
+use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
+use log::{info, warn};
+use solana_sdk::signature::Signature;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, serde_derive::Serialize)]
+struct Receipt<'a> {
+    signature: String,
+    timestamp: u64,
+    #[serde(flatten)]
+    summary: &'a TransferSummary,
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+fn rotate(path: &Path, max_bytes: u64) -> Result<()> {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Ok(());
+    };
+    if metadata.len() < max_bytes {
+        return Ok(());
+    }
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("receipts");
+    let rotated = path.with_file_name(format!("{}.{}.jsonl.gz", stem, unix_timestamp()));
+
+    let mut encoder = GzEncoder::new(File::create(&rotated)?, Compression::default());
+    std::io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)?;
+
+    info!("受領ログをローテートしました: {}", rotated.display());
+    Ok(())
+}
+
+impl SolanaTransactionManager {
+    fn write_receipt(&self, signature: &Signature, summary: &TransferSummary) -> Result<()> {
+        let Some(receipts) = &self.config.receipts else {
+            return Ok(());
+        };
+        let path = Path::new(&receipts.path);
+
+        if let Some(max_bytes) = receipts.rotate_bytes {
+            rotate(path, max_bytes)?;
+        }
+
+        let receipt = Receipt {
+            signature: signature.to_string(),
+            timestamp: unix_timestamp(),
+            summary,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&receipt)?)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn record_receipt(&self, signature: &Signature, summary: &TransferSummary) {
+        if let Err(e) = self.write_receipt(signature, summary) {
+            warn!("受領ログの書き込みに失敗しました: {}", e);
+        }
+    }
+
+    pub(crate) fn record_signature(&self, signature: &Signature) {
+        let Some(path) = &self.config.output.signature_file else {
+            return;
+        };
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", signature));
+        if let Err(e) = result {
+            warn!(
+                "シグネチャファイル {} への書き込みに失敗しました: {}",
+                path, e
+            );
+        }
+    }
+}
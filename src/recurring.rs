@@ -0,0 +1,58 @@
+use crate::{SendOptions, SolanaTransactionManager};
+use anyhow::Result;
+use log::info;
+use std::path::Path;
+use std::time::Duration;
+
+impl SolanaTransactionManager {
+    pub fn send_recurring(
+        &self,
+        options: &SendOptions,
+        every: Duration,
+        count: Option<u64>,
+        pause_file: &Path,
+    ) -> Result<()> {
+        let mut sent = 0;
+        let mut paused = false;
+        loop {
+            if pause_file.exists() {
+                if !paused {
+                    info!(
+                        "{} があるため送金を一時停止します (削除すると再開)",
+                        pause_file.display()
+                    );
+                    paused = true;
+                }
+            } else {
+                if paused {
+                    info!("{} が削除されたため送金を再開します", pause_file.display());
+                    paused = false;
+                }
+
+                match self.resume_pending(options)? {
+                    Some(signature) => {
+                        self.record_signature(&signature);
+                        println!("TX成功!: {}", signature);
+                    }
+                    None => match self.send_transaction(options) {
+                        Ok(outcome) => {
+                            self.notify_success(&outcome);
+                            println!("{}", self.success_message(&outcome));
+                        }
+                        Err(e) => {
+                            self.notify_failure(&e);
+                            return Err(e);
+                        }
+                    },
+                }
+
+                sent += 1;
+                if count.is_some_and(|count| sent >= count) {
+                    return Ok(());
+                }
+            }
+
+            std::thread::sleep(every);
+        }
+    }
+}
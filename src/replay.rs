@@ -0,0 +1,70 @@
+use crate::{format::format_sol, SendOptions, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use log::warn;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_SCAN_LIMIT: usize = 20;
+
+impl SolanaTransactionManager {
+    // Heuristic guard against accidental re-runs: look for a successful transfer of the same
+    // amount to the same receiver among the sender's recent signatures.
+    pub(crate) fn check_recent_duplicate(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        mint: Option<&Pubkey>,
+        amount: u64,
+        options: &SendOptions,
+    ) -> Result<()> {
+        let Some(window) = self.config.transaction.duplicate_window else {
+            return Ok(());
+        };
+        let cutoff = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64 - window as i64;
+
+        let signatures = self.client.get_signatures_for_address_with_config(
+            sender,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(
+                    self.config
+                        .transaction
+                        .duplicate_scan_limit
+                        .unwrap_or(DEFAULT_SCAN_LIMIT),
+                ),
+                ..Default::default()
+            },
+        )?;
+
+        for info in signatures {
+            if info.err.is_some() || info.block_time.is_some_and(|time| time < cutoff) {
+                continue;
+            }
+            let signature = Signature::from_str(&info.signature)
+                .map_err(|e| anyhow!("Invalid signature {}: {}", info.signature, e))?;
+            if self.received_amount(&signature, receiver, mint)? != Some(amount as i128) {
+                continue;
+            }
+
+            let message = format!(
+                "{}秒以内に同じ宛先 {} へ同額 ({}) の送金があります: {}",
+                window,
+                receiver,
+                if mint.is_some() {
+                    amount.to_string()
+                } else {
+                    format_sol(amount)
+                },
+                signature
+            );
+            if self.config.transaction.abort_on_duplicate && !options.force {
+                return Err(anyhow!("{} (--forceで送金)", message));
+            }
+            warn!("{}", message);
+            return Ok(());
+        }
+
+        Ok(())
+    }
+}
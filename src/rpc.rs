@@ -0,0 +1,666 @@
+use crate::{error::TransferError, NetworkConfig, SolanaTransactionManager};
+use async_trait::async_trait;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use log::{info, warn};
+use reqwest::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    StatusCode,
+};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use solana_client::{
+    client_error::{ClientErrorKind, Result},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_custom_error::{
+        NodeUnhealthyErrorData, JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+        JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+    },
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+pub type CertFingerprint = [u8; 32];
+
+const DEFAULT_RATE_LIMIT_RETRIES: u32 = 5;
+const DEFAULT_MAX_RETRY_AFTER: u64 = 120;
+
+#[derive(Debug, Clone)]
+pub struct EndpointOptions {
+    pub timeout: Duration,
+    pub pins: Vec<CertFingerprint>,
+    pub rate_limit_retries: u32,
+    pub max_retry_after: Duration,
+}
+
+// Accepts only servers presenting one of the pinned certificates (SHA-256 of the DER bytes),
+// independent of the CA chain, so a hijacked name with a valid certificate is still refused.
+struct PinnedCertVerifier {
+    fingerprints: Vec<CertFingerprint>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = solana_sdk::hash::hash(&end_entity.0).to_bytes();
+        if self.fingerprints.contains(&fingerprint) {
+            return Ok(ServerCertVerified::assertion());
+        }
+        Err(rustls::Error::General(format!(
+            "certificate of {:?} does not match any pinned fingerprint (got {})",
+            server_name,
+            hex(&fingerprint)
+        )))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_fingerprint(text: &str) -> std::result::Result<CertFingerprint, TransferError> {
+    let invalid = || {
+        TransferError::Config(format!(
+            "Invalid tls_cert_sha256 '{}': expected 64 hex digits",
+            text
+        ))
+    };
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !matches!(byte, b':' | b' '))
+        .collect();
+    if digits.len() != 64 {
+        return Err(invalid());
+    }
+
+    let mut fingerprint = [0; 32];
+    for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks(2)) {
+        let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+    }
+    Ok(fingerprint)
+}
+
+impl NetworkConfig {
+    pub(crate) fn endpoint_options(&self) -> std::result::Result<EndpointOptions, TransferError> {
+        Ok(EndpointOptions {
+            timeout: Duration::from_secs(30),
+            pins: self
+                .tls_cert_sha256
+                .iter()
+                .map(|text| parse_fingerprint(text))
+                .collect::<std::result::Result<_, _>>()?,
+            rate_limit_retries: self
+                .rate_limit_retries
+                .unwrap_or(DEFAULT_RATE_LIMIT_RETRIES),
+            max_retry_after: Duration::from_secs(
+                self.max_retry_after.unwrap_or(DEFAULT_MAX_RETRY_AFTER),
+            ),
+        })
+    }
+
+    // Refuses RPC URLs outside allowed_hosts, and plain http when certificates are pinned,
+    // before anything signed can be sent to them.
+    pub(crate) fn check_endpoints(&self) -> std::result::Result<(), TransferError> {
+        for url in self.rpc_urls() {
+            let parsed = reqwest::Url::parse(&url)
+                .map_err(|e| TransferError::Config(format!("Invalid RPC URL {}: {}", url, e)))?;
+            let host = parsed.host_str().unwrap_or_default();
+            if !self.allowed_hosts.is_empty()
+                && !self
+                    .allowed_hosts
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(host))
+            {
+                return Err(TransferError::Config(format!(
+                    "RPC host {} is not in allowed_hosts",
+                    host
+                )));
+            }
+            if !self.tls_cert_sha256.is_empty() && parsed.scheme() != "https" {
+                return Err(TransferError::Config(format!(
+                    "RPC URL {} must use https when tls_cert_sha256 is set",
+                    url
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn http_client(
+    timeout: Duration,
+    pins: &[CertFingerprint],
+) -> std::result::Result<reqwest::Client, TransferError> {
+    let builder = reqwest::Client::builder()
+        .default_headers(HttpSender::default_headers())
+        .timeout(timeout)
+        .pool_idle_timeout(timeout);
+    let builder = if pins.is_empty() {
+        builder
+    } else {
+        builder.use_preconfigured_tls(
+            rustls::ClientConfig::builder()
+                .with_safe_defaults()
+                .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                    fingerprints: pins.to_vec(),
+                }))
+                .with_no_client_auth(),
+        )
+    };
+    builder
+        .build()
+        .map_err(|e| TransferError::Config(format!("Failed to build RPC client: {}", e)))
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    parse_retry_after(response.headers().get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+// Only the delay-seconds form; an HTTP-date falls back to exponential backoff.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+fn rpc_error(error: &serde_json::Value) -> RpcError {
+    #[derive(serde_derive::Deserialize)]
+    struct ErrorObject {
+        code: i64,
+        message: String,
+    }
+
+    let Ok(object) = serde_json::from_value::<ErrorObject>(error.clone()) else {
+        return RpcError::RpcRequestError(format!(
+            "Failed to deserialize RPC error response: {}",
+            error
+        ));
+    };
+    let data = match object.code {
+        JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
+            serde_json::from_value(error["data"].clone())
+                .map(RpcResponseErrorData::SendTransactionPreflightFailure)
+                .unwrap_or(RpcResponseErrorData::Empty)
+        }
+        JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => {
+            serde_json::from_value::<NodeUnhealthyErrorData>(error["data"].clone())
+                .map(|data| RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: data.num_slots_behind,
+                })
+                .unwrap_or(RpcResponseErrorData::Empty)
+        }
+        _ => RpcResponseErrorData::Empty,
+    };
+    RpcError::RpcResponseError {
+        code: object.code,
+        message: object.message,
+        data,
+    }
+}
+
+// The JSON-RPC transport of HttpSender, except that a 429 waits for the server's Retry-After
+// (falling back to exponential backoff) and gives up when the server asks for too long.
+struct HttpEndpoint {
+    client: reqwest::Client,
+    url: String,
+    request_id: AtomicU64,
+    rate_limit_retries: u32,
+    max_retry_after: Duration,
+    stats: RwLock<RpcTransportStats>,
+}
+
+impl HttpEndpoint {
+    fn new(url: &str, options: &EndpointOptions) -> std::result::Result<Self, TransferError> {
+        Ok(Self {
+            client: http_client(options.timeout, &options.pins)?,
+            url: url.to_string(),
+            request_id: AtomicU64::new(0),
+            rate_limit_retries: options.rate_limit_retries,
+            max_retry_after: options.max_retry_after,
+            stats: RwLock::new(RpcTransportStats::default()),
+        })
+    }
+
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let started = Instant::now();
+        let mut rate_limited = Duration::ZERO;
+        let result = self.post(request, params, &mut rate_limited).await;
+
+        let mut stats = self.stats.write().unwrap();
+        stats.request_count += 1;
+        stats.elapsed_time += started.elapsed();
+        stats.rate_limited_time += rate_limited;
+        result
+    }
+
+    async fn post(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+        rate_limited: &mut Duration,
+    ) -> Result<serde_json::Value> {
+        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let body = request.build_request_json(request_id, params).to_string();
+
+        let mut retries = self.rate_limit_retries;
+        let mut backoff = Duration::from_millis(500);
+        loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && retries > 0 {
+                let delay = match retry_after(&response) {
+                    Some(delay) if delay > self.max_retry_after => {
+                        warn!(
+                            "RPC {} が {}秒後の再試行を求めています (max_retry_after {}秒を超えるため待ちません)",
+                            self.url,
+                            delay.as_secs(),
+                            self.max_retry_after.as_secs()
+                        );
+                        return Err(response.error_for_status().unwrap_err().into());
+                    }
+                    Some(delay) => {
+                        info!(
+                            "RPC {} がレート制限中です。Retry-Afterに従い{}秒待ちます",
+                            self.url,
+                            delay.as_secs()
+                        );
+                        delay
+                    }
+                    None => {
+                        let delay = backoff;
+                        backoff *= 2;
+                        warn!(
+                            "RPC {} がレート制限中です。{}ms待って再試行します",
+                            self.url,
+                            delay.as_millis()
+                        );
+                        delay
+                    }
+                };
+                retries -= 1;
+                *rate_limited += delay;
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(response.error_for_status().unwrap_err().into());
+            }
+
+            let mut json = response.json::<serde_json::Value>().await?;
+            if json["error"].is_object() {
+                return Err(rpc_error(&json["error"]).into());
+            }
+            return Ok(json["result"].take());
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde_derive::Serialize)]
+pub struct EndpointStats {
+    pub url: String,
+    pub successes: u64,
+    pub failures: u64,
+    pub average_latency_ms: Option<f64>,
+    #[serde(skip)]
+    total_latency: Duration,
+}
+
+impl EndpointStats {
+    fn new(url: &str) -> Self {
+        EndpointStats {
+            url: url.to_string(),
+            successes: 0,
+            failures: 0,
+            average_latency_ms: None,
+            total_latency: Duration::ZERO,
+        }
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.successes += 1;
+        self.total_latency += latency;
+        self.average_latency_ms =
+            Some(self.total_latency.as_secs_f64() * 1000.0 / self.successes as f64);
+    }
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct RpcSummary<'a> {
+    endpoints: &'a [EndpointStats],
+}
+
+pub type SharedStats = Arc<Mutex<Vec<EndpointStats>>>;
+pub type SharedLimiter = Arc<DefaultDirectRateLimiter>;
+
+// max_requests_per_second is one budget for the whole run, so every sender takes its permits
+// from the same limiter.
+pub fn rate_limiter(requests_per_second: Option<u32>) -> Option<SharedLimiter> {
+    requests_per_second
+        .and_then(NonZeroU32::new)
+        .map(|rate| Arc::new(RateLimiter::direct(Quota::per_second(rate))))
+}
+
+pub struct RateLimitedSender {
+    endpoints: Vec<HttpEndpoint>,
+    stats: SharedStats,
+    limiter: Option<SharedLimiter>,
+}
+
+impl RateLimitedSender {
+    pub fn new(
+        urls: &[String],
+        limiter: Option<SharedLimiter>,
+        options: &EndpointOptions,
+    ) -> std::result::Result<Self, TransferError> {
+        let stats = Arc::new(Mutex::new(
+            urls.iter().map(|url| EndpointStats::new(url)).collect(),
+        ));
+        Self::with_stats(urls, limiter, options, stats)
+    }
+
+    pub fn with_stats(
+        urls: &[String],
+        limiter: Option<SharedLimiter>,
+        options: &EndpointOptions,
+        stats: SharedStats,
+    ) -> std::result::Result<Self, TransferError> {
+        Ok(Self {
+            endpoints: urls
+                .iter()
+                .map(|url| HttpEndpoint::new(url, options))
+                .collect::<std::result::Result<_, _>>()?,
+            stats,
+            limiter,
+        })
+    }
+
+    pub fn stats(&self) -> SharedStats {
+        self.stats.clone()
+    }
+
+    fn record(&self, index: usize, latency: Option<Duration>) {
+        let mut stats = self.stats.lock().unwrap();
+        match latency {
+            Some(latency) => stats[index].record_success(latency),
+            None => stats[index].failures += 1,
+        }
+    }
+}
+
+impl SolanaTransactionManager {
+    // A client for one endpoint that still honours the certificate pins.
+    pub(crate) fn endpoint_client(
+        &self,
+        url: &str,
+        commitment: CommitmentConfig,
+    ) -> std::result::Result<RpcClient, TransferError> {
+        Ok(RpcClient::new_sender(
+            RateLimitedSender::new(
+                &[url.to_string()],
+                self.rate_limiter.clone(),
+                &self.endpoint_options,
+            )?,
+            RpcClientConfig::with_commitment(commitment),
+        ))
+    }
+
+    pub(crate) fn print_rpc_summary(&self) {
+        let stats = self.rpc_stats.lock().unwrap();
+        if self.config.output.json {
+            match serde_json::to_string(&RpcSummary { endpoints: &stats }) {
+                Ok(summary) => println!("{}", summary),
+                Err(e) => warn!("RPCの成績をJSONにできませんでした: {}", e),
+            }
+            return;
+        }
+
+        println!("RPCエンドポイントの成績:");
+        for endpoint in stats.iter() {
+            let latency = endpoint
+                .average_latency_ms
+                .map(|ms| format!("{:.1}ms", ms))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "  {} 成功 {} / 失敗 {} / 平均応答 {}",
+                endpoint.url, endpoint.successes, endpoint.failures, latency
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for RateLimitedSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        if let Some(limiter) = &self.limiter {
+            limiter.until_ready().await;
+        }
+
+        let last = self.endpoints.len() - 1;
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            let started = Instant::now();
+            match endpoint.send(request, params.clone()).await {
+                Err(e)
+                    if index < last
+                        && matches!(
+                            e.kind(),
+                            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_)
+                        ) =>
+                {
+                    self.record(index, None);
+                    warn!(
+                        "RPC {} に接続できないため次のエンドポイントを使います: {}",
+                        endpoint.url, e
+                    );
+                }
+                Err(e)
+                    if index < last
+                        && matches!(
+                            e.kind(),
+                            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+                                if *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+                        ) =>
+                {
+                    self.record(index, None);
+                    warn!(
+                        "RPC {} がmin_context_slotに達していないため次のエンドポイントを使います",
+                        endpoint.url
+                    );
+                }
+                result => {
+                    self.record(index, result.is_ok().then(|| started.elapsed()));
+                    return result;
+                }
+            }
+        }
+        unreachable!("at least one RPC endpoint is configured")
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.endpoints[0].stats.read().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        self.endpoints[0].url.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    // Answers connections with the given raw HTTP responses in turn, repeating the last one.
+    fn serve(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                let response = &responses[index.min(responses.len() - 1)];
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        url
+    }
+
+    fn result_response(result: &str) -> String {
+        let body = format!(r#"{{"jsonrpc":"2.0","result":{},"id":0}}"#, result);
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn serve_result(result: &str) -> String {
+        serve(vec![result_response(result)])
+    }
+
+    fn unreachable_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn options() -> EndpointOptions {
+        EndpointOptions {
+            timeout: Duration::from_secs(5),
+            pins: Vec::new(),
+            rate_limit_retries: 0,
+            max_retry_after: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn fingerprints_accept_separators_and_reject_bad_lengths() {
+        let hex_digits = "ab".repeat(32);
+        assert_eq!(parse_fingerprint(&hex_digits).unwrap(), [0xab; 32]);
+        let separated = vec!["AB"; 32].join(":");
+        assert_eq!(parse_fingerprint(&separated).unwrap(), [0xab; 32]);
+        assert!(parse_fingerprint(&"ab".repeat(31)).is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn summary_json_lists_every_endpoint() {
+        let mut used = EndpointStats::new("http://a");
+        used.record_success(Duration::from_millis(10));
+        used.record_success(Duration::from_millis(30));
+        let mut failed = EndpointStats::new("http://b");
+        failed.failures = 1;
+
+        let json = serde_json::to_value(RpcSummary {
+            endpoints: &[used, failed],
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "endpoints": [
+                    {"url": "http://a", "successes": 2, "failures": 0, "average_latency_ms": 20.0},
+                    {"url": "http://b", "successes": 0, "failures": 1, "average_latency_ms": null},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn retry_after_reads_delay_seconds() {
+        assert_eq!(parse_retry_after("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2026 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_request_is_retried_after_the_server_delay() {
+        let url = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            result_response("7"),
+        ]);
+        let sender = RateLimitedSender::new(
+            &[url],
+            None,
+            &EndpointOptions {
+                rate_limit_retries: 1,
+                ..options()
+            },
+        )
+        .unwrap();
+
+        let result = sender
+            .send(RpcRequest::GetSlot, serde_json::Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(result, serde_json::json!(7));
+        assert_eq!(sender.get_transport_stats().request_count, 1);
+    }
+
+    #[tokio::test]
+    async fn stats_are_counted_per_endpoint_across_failover() {
+        let urls = vec![unreachable_url(), serve_result("42")];
+        let sender = RateLimitedSender::new(&urls, None, &options()).unwrap();
+
+        for _ in 0..2 {
+            let result = sender
+                .send(RpcRequest::GetSlot, serde_json::Value::Null)
+                .await
+                .unwrap();
+            assert_eq!(result, serde_json::json!(42));
+        }
+
+        let stats = sender.stats();
+        let stats = stats.lock().unwrap();
+        assert_eq!((stats[0].successes, stats[0].failures), (0, 2));
+        assert_eq!((stats[1].successes, stats[1].failures), (2, 0));
+        assert!(stats[0].average_latency_ms.is_none());
+        assert!(stats[1].average_latency_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn senders_share_one_rate_limit() {
+        let url = vec![serve_result("1")];
+        let limiter = rate_limiter(Some(1));
+        let first = RateLimitedSender::new(&url, limiter.clone(), &options()).unwrap();
+        let second = RateLimitedSender::new(&url, limiter, &options()).unwrap();
+
+        first
+            .send(RpcRequest::GetSlot, serde_json::Value::Null)
+            .await
+            .unwrap();
+        let started = Instant::now();
+        second
+            .send(RpcRequest::GetSlot, serde_json::Value::Null)
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+
+#[cfg(feature = "keyring")]
+pub fn load_secret(service: &str, user: &str) -> Result<String> {
+    keyring::Entry::new(service, user)?
+        .get_password()
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read key from keyring {}/{}: {}",
+                service,
+                user,
+                e
+            )
+        })
+}
+
+#[cfg(feature = "keyring")]
+pub fn store_secret(service: &str, user: &str, secret: &str) -> Result<()> {
+    keyring::Entry::new(service, user)?
+        .set_password(secret)
+        .map_err(|e| anyhow::anyhow!("Failed to store key in keyring {}/{}: {}", service, user, e))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn load_secret(_service: &str, _user: &str) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Keyring support is not enabled; rebuild with `--features keyring`"
+    ))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store_secret(_service: &str, _user: &str, _secret: &str) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Keyring support is not enabled; rebuild with `--features keyring`"
+    ))
+}
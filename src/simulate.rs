@@ -0,0 +1,54 @@
+use crate::SolanaTransactionManager;
+use anyhow::{anyhow, Result};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulateOptions {
+    pub sig_verify: bool,
+    pub replace_recent_blockhash: bool,
+}
+
+impl SolanaTransactionManager {
+    // Signs the configured transfer and runs it through simulateTransaction without sending.
+    // The RPC rejects sig_verify together with replace_recent_blockhash, so sig_verify wins.
+    pub fn simulate_transfer(&self, options: SimulateOptions) -> Result<()> {
+        let sender_keypair = self.create_sender_keypair()?;
+        let amount = self.amount()?;
+        let inputs = self.transfer_inputs(&sender_keypair.pubkey())?;
+        let instructions =
+            self.build_transfer_instructions(&sender_keypair.pubkey(), amount, &inputs)?;
+        let transaction = self.sign_transaction(&instructions, sender_keypair.as_ref())?;
+        self.trace_message(&transaction.message);
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: options.sig_verify,
+            replace_recent_blockhash: options.replace_recent_blockhash && !options.sig_verify,
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        };
+        println!(
+            "シミュレーション (sig_verify: {}, replace_recent_blockhash: {})",
+            config.sig_verify, config.replace_recent_blockhash
+        );
+        let result = self
+            .client
+            .simulate_transaction_with_config(&transaction, config)?
+            .value;
+
+        if let Some(units) = result.units_consumed {
+            println!("消費コンピュートユニット: {}", units);
+        }
+        for log in result.logs.unwrap_or_default() {
+            println!("  {}", log);
+        }
+
+        match result.err {
+            Some(err) => Err(anyhow!("Simulation failed: {}", err)),
+            None => {
+                println!("シミュレーション成功 (送信はしていません)");
+                Ok(())
+            }
+        }
+    }
+}
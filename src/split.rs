@@ -0,0 +1,161 @@
+use crate::{
+    amount::Amount, error::TransferError, format::format_sol, SendOptions,
+    SolanaTransactionManager, TransferCost, TransferSummary,
+};
+use anyhow::Result;
+use solana_sdk::signature::Signature;
+
+// Equal chunks with the remainder added to the last one.
+fn chunks(total: u64, parts: u32) -> Result<Vec<u64>> {
+    let base = total / parts as u64;
+    if base == 0 {
+        return Err(TransferError::Config(format!(
+            "Amount {} cannot be split into {} non-zero transfers",
+            total, parts
+        ))
+        .into());
+    }
+
+    let mut chunks = vec![base; parts as usize];
+    if let Some(last) = chunks.last_mut() {
+        *last += total % parts as u64;
+    }
+    Ok(chunks)
+}
+
+impl SolanaTransactionManager {
+    fn check_split_balance(&self, total: u64, first_chunk: u64, parts: u32) -> Result<u64> {
+        let sender = self.create_sender_keypair()?.pubkey();
+        let balance = self.get_balance(&sender)?;
+        let fees = self.estimate_transfer_fee(&sender, first_chunk)? * parts as u64;
+        let lamports = if self.config.token.is_some() {
+            0
+        } else {
+            total
+        };
+        let required = lamports + fees + self.min_balance(balance);
+        if balance < required {
+            return Err(TransferError::InsufficientBalance { balance, required }.into());
+        }
+
+        println!(
+            "{} を{}回に分けて送金します (手数料 {}回分 {})",
+            if self.config.token.is_some() {
+                total.to_string()
+            } else {
+                format_sol(total)
+            },
+            parts,
+            parts,
+            format_sol(fees)
+        );
+        Ok(fees)
+    }
+
+    // max_total_cost, the large-transfer prompt and the approval webhook apply to the whole
+    // split, so chunks below the thresholds cannot move a larger amount unchecked.
+    fn check_split_total(&self, options: &SendOptions, total: u64, fees: u64) -> Result<()> {
+        let sender = self.create_sender_keypair()?.pubkey();
+        let receiver = self.receiver_pubkey()?;
+        let mint = self.token_mint()?;
+        let lamports = if mint.is_some() { 0 } else { total };
+        let account_rent = match mint {
+            Some(mint) => self.token_account_rent(&receiver, &mint)?,
+            None => 0,
+        };
+        self.check_total_cost(&TransferCost {
+            amount: lamports,
+            fee: fees,
+            account_rent,
+        })?;
+
+        if mint.is_none() {
+            self.confirm_large_transfer(lamports, options)?;
+        }
+        if let Some(url) = &self.config.transaction.approval_webhook_url {
+            self.request_approval(
+                url,
+                &TransferSummary {
+                    sender: sender.to_string(),
+                    receiver: receiver.to_string(),
+                    amount: total,
+                    mint: mint.map(|mint| mint.to_string()),
+                    fee: fees,
+                    label: self.config.transaction.label.clone(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    // Sends the configured amount as split_into sequential transfers, each confirmed before the
+    // next. Later chunks skip the duplicate check since they repeat the first one on purpose.
+    pub fn send_split(&mut self, options: &SendOptions, parts: u32) -> Result<Vec<Signature>> {
+        let total = self.amount()?;
+        let chunks = chunks(total, parts)?;
+        if self.fixed_blockhash.is_some() && chunks.iter().skip(1).any(|chunk| *chunk == chunks[0])
+        {
+            return Err(TransferError::Config(
+                "--blockhash cannot be used with split_into: equal chunks signed over one blockhash are identical transactions".to_string(),
+            )
+            .into());
+        }
+        let fees = self.check_split_balance(total, chunks[0], parts)?;
+        self.check_split_total(options, total, fees)?;
+
+        let mut signatures = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            self.config.transaction.amount = Amount::Lamports(*chunk);
+            let options = SendOptions {
+                skip_duplicate_check: options.skip_duplicate_check || index > 0,
+                whole_transfer_checked: true,
+                ..*options
+            };
+            let outcome = match self.send_transaction(&options) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    self.notify_failure(&e);
+                    if !signatures.is_empty() {
+                        println!(
+                            "{}/{}件目で失敗しました。送金済み: {}",
+                            index + 1,
+                            chunks.len(),
+                            signatures
+                                .iter()
+                                .map(Signature::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                    }
+                    return Err(e);
+                }
+            };
+            self.notify_success(&outcome);
+            println!(
+                "[{}/{}] TX成功!: {}",
+                index + 1,
+                chunks.len(),
+                outcome.signature
+            );
+            signatures.push(outcome.signature);
+        }
+
+        println!("分割送金のシグネチャ:");
+        for signature in &signatures {
+            println!("  {}", signature);
+        }
+        Ok(signatures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_add_the_remainder_to_the_last_transfer() {
+        assert_eq!(chunks(10, 3).unwrap(), vec![3, 3, 4]);
+        assert_eq!(chunks(9, 3).unwrap(), vec![3, 3, 3]);
+        assert!(chunks(2, 3).is_err());
+    }
+}
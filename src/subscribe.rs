@@ -0,0 +1,101 @@
+use crate::{error::TransferError, SolanaTransactionManager};
+use anyhow::Result;
+use log::{info, warn};
+use solana_client::{
+    pubsub_client::{AccountSubscription, PubsubClient},
+    rpc_config::RpcAccountInfoConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::time::{Duration, Instant};
+
+pub(crate) struct AccountWatch {
+    subscription: AccountSubscription,
+    target: u64,
+}
+
+impl SolanaTransactionManager {
+    // ws_url, or the rpc_url with ws(s):// and the next port like the Solana CLI derives it
+    // (8899 -> 8900 for a local validator).
+    fn websocket_url(&self) -> Result<String> {
+        if let Some(url) = &self.config.network.ws_url {
+            return Ok(url.clone());
+        }
+        let mut url = reqwest::Url::parse(&self.config.network.rpc_url)
+            .map_err(|e| TransferError::Config(format!("Invalid rpc_url: {}", e)))?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        let port = url.port().map(|port| port + 1);
+        url.set_scheme(scheme)
+            .and_then(|_| url.set_port(port))
+            .map_err(|_| TransferError::Config("Cannot derive ws_url from rpc_url".to_string()))?;
+        Ok(url.to_string())
+    }
+
+    // Subscribes to the receiver before the transfer is sent so its credit cannot be missed.
+    // Failing to subscribe only leaves plain signature-status polling in place.
+    pub(crate) fn watch_receiver(&self, receiver: &Pubkey, lamports: u64) {
+        if !self.config.transaction.confirm_by_account || lamports == 0 {
+            return;
+        }
+
+        let config = RpcAccountInfoConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        };
+        let subscription = self.websocket_url().and_then(|url| {
+            Ok(PubsubClient::account_subscribe(
+                &url,
+                receiver,
+                Some(config),
+            )?)
+        });
+        let watch = subscription.and_then(|subscription| {
+            let baseline = self
+                .client
+                .get_balance_with_commitment(receiver, CommitmentConfig::confirmed())?
+                .value;
+            Ok(AccountWatch {
+                subscription,
+                target: baseline + lamports,
+            })
+        });
+        match watch {
+            Ok(watch) => *self.account_watch.lock().unwrap() = Some(watch),
+            Err(e) => warn!(
+                "受取アカウントを購読できませんでした。シグネチャの状態だけで確認します: {}",
+                e
+            ),
+        }
+    }
+
+    pub(crate) fn stop_watching_receiver(&self) {
+        self.account_watch.lock().unwrap().take();
+    }
+
+    // Waits up to `timeout` for a notification showing the receiver at the expected balance.
+    // This is only a hint to check the signature status right away: an unrelated credit looks
+    // the same, so the caller must never treat it as confirmation.
+    pub(crate) fn wait_for_receiver_credit(&self, timeout: Duration) -> bool {
+        let watch = self.account_watch.lock().unwrap();
+        let Some(watch) = watch.as_ref() else {
+            std::thread::sleep(timeout);
+            return false;
+        };
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match watch.subscription.1.recv_timeout(remaining) {
+                Ok(update) if update.value.lamports >= watch.target => {
+                    info!("受取アカウントの残高が増えたため、シグネチャの状態を確認します");
+                    return true;
+                }
+                Ok(_) => {}
+                Err(e) if e.is_timeout() => return false,
+                Err(_) => {
+                    std::thread::sleep(remaining);
+                    return false;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{amount::Amount, error::TransferError, SolanaTransactionManager};
+use anyhow::{anyhow, Result};
+use solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+use std::str::FromStr;
+
+impl SolanaTransactionManager {
+    pub(crate) fn token_mint(&self) -> Result<Option<Pubkey>> {
+        self.config
+            .token
+            .as_ref()
+            .map(|token| {
+                Pubkey::from_str(&token.mint)
+                    .map_err(|e| TransferError::Config(format!("Invalid token mint: {}", e)).into())
+            })
+            .transpose()
+    }
+
+    pub(crate) fn sender_token_account(&self, sender: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+        match self
+            .config
+            .token
+            .as_ref()
+            .and_then(|token| token.sender_token_account.as_ref())
+        {
+            Some(address) => Pubkey::from_str(address)
+                .map_err(|e| anyhow!("Invalid sender token account: {}", e)),
+            None => Ok(get_associated_token_address(sender, mint)),
+        }
+    }
+
+    pub(crate) fn load_token_account(
+        &self,
+        token_account: &Pubkey,
+        mint: &Pubkey,
+        role: &str,
+    ) -> Result<TokenAccount> {
+        let account = self
+            .client
+            .get_account(token_account)
+            .map_err(|e| anyhow!("{} token account {} not found: {}", role, token_account, e))?;
+
+        if account.owner != spl_token::id() {
+            return Err(anyhow!(
+                "{} token account {} is owned by {}, expected the SPL Token program {}",
+                role,
+                token_account,
+                account.owner,
+                spl_token::id()
+            ));
+        }
+
+        let state = TokenAccount::unpack(&account.data).map_err(|e| {
+            anyhow!(
+                "Failed to decode {} token account {}: {}",
+                role.to_lowercase(),
+                token_account,
+                e
+            )
+        })?;
+
+        if state.mint != *mint {
+            return Err(anyhow!(
+                "Token mint mismatch. {} token account {} holds mint {}, configured mint is {}",
+                role,
+                token_account,
+                state.mint,
+                mint
+            ));
+        }
+
+        Ok(state)
+    }
+
+    pub(crate) fn check_sender_token_account(
+        &self,
+        token_account: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let state = self.load_token_account(token_account, mint, "Sender")?;
+
+        if state.amount < amount {
+            return Err(TransferError::InsufficientTokenBalance {
+                balance: state.amount,
+                required: amount,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn receiver_is_pda(&self) -> bool {
+        self.config
+            .token
+            .as_ref()
+            .is_some_and(|token| token.receiver_is_pda)
+    }
+
+    pub(crate) fn token_account_rent(&self, receiver: &Pubkey, mint: &Pubkey) -> Result<u64> {
+        if self.receiver_is_pda() {
+            return Ok(0);
+        }
+
+        let destination = get_associated_token_address(receiver, mint);
+        if self.client.get_account(&destination).is_ok() {
+            return Ok(0);
+        }
+
+        Ok(self
+            .client
+            .get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?)
+    }
+
+    pub(crate) fn check_receiver_curve(&self, receiver: &Pubkey) -> Result<()> {
+        if !self.receiver_is_pda() && !receiver.is_on_curve() {
+            return Err(anyhow!(
+                "Receiver {} is off-curve; set receiver_is_pda = true to send to a program-owned token account",
+                receiver
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_token_transfer(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        self.check_receiver_curve(receiver)?;
+
+        let source = self.sender_token_account(sender, mint)?;
+        self.check_sender_token_account(&source, mint, amount)?;
+
+        if self.receiver_is_pda() {
+            self.load_token_account(receiver, mint, "Receiver")?;
+        }
+
+        self.check_token_amount(mint, amount)
+    }
+
+    fn mint_state(&self, mint: &Pubkey) -> Result<Mint> {
+        if let Some(state) = self.mints.lock().unwrap().get(mint) {
+            return Ok(*state);
+        }
+
+        let mint_account = self.client.get_account(mint)?;
+        let state = Mint::unpack(&mint_account.data)
+            .map_err(|e| anyhow!("Failed to decode mint {}: {}", mint, e))?;
+        self.mints.lock().unwrap().insert(*mint, state);
+
+        Ok(state)
+    }
+
+    pub(crate) fn mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        Ok(self.mint_state(mint)?.decimals)
+    }
+
+    // Token amounts are raw base units, so a SOL-style decimal amount or one larger than the
+    // whole supply almost always means the decimals were applied wrongly.
+    pub(crate) fn check_token_amount(&self, mint: &Pubkey, amount: u64) -> Result<()> {
+        let state = self.mint_state(mint)?;
+        if let Amount::Sol(text) = &self.config.transaction.amount {
+            return Err(TransferError::Config(format!(
+                "Token amount '{}' must be an integer in base units of mint {} ({} decimals)",
+                text, mint, state.decimals
+            ))
+            .into());
+        }
+        if amount > state.supply {
+            return Err(TransferError::Config(format!(
+                "Token amount {} exceeds the total supply {} of mint {}; amounts are in base units ({} decimals)",
+                amount, state.supply, mint, state.decimals
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn token_transfer_instructions(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<Vec<Instruction>> {
+        let source = self.sender_token_account(sender, mint)?;
+
+        let mut instructions = Vec::new();
+        let destination = if self.receiver_is_pda() {
+            *receiver
+        } else {
+            instructions.push(create_associated_token_account_idempotent(
+                sender,
+                receiver,
+                mint,
+                &spl_token::id(),
+            ));
+            get_associated_token_address(receiver, mint)
+        };
+
+        instructions.push(spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            mint,
+            &destination,
+            sender,
+            &[],
+            amount,
+            decimals,
+        )?);
+
+        Ok(instructions)
+    }
+}
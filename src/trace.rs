@@ -0,0 +1,44 @@
+use crate::SolanaTransactionManager;
+use solana_sdk::message::Message;
+
+pub fn print_message(message: &Message) {
+    println!(
+        "トランザクション: 命令{}件 / アカウント{}件 / blockhash {}",
+        message.instructions.len(),
+        message.account_keys.len(),
+        message.recent_blockhash
+    );
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        println!(
+            "  #{} program {} (data {} bytes)",
+            index,
+            message.account_keys[instruction.program_id_index as usize],
+            instruction.data.len()
+        );
+        for account in &instruction.accounts {
+            let account = *account as usize;
+            println!(
+                "      {} {}{}",
+                message.account_keys[account],
+                if message.is_signer(account) {
+                    "signer "
+                } else {
+                    ""
+                },
+                if message.is_writable(account) {
+                    "writable"
+                } else {
+                    "readonly"
+                }
+            );
+        }
+    }
+}
+
+impl SolanaTransactionManager {
+    pub(crate) fn trace_message(&self, message: &Message) {
+        if self.config.output.trace_tx {
+            print_message(message);
+        }
+    }
+}
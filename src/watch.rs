@@ -0,0 +1,39 @@
+use crate::{error::TransferError, format::format_sol, SolanaTransactionManager};
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::time::Duration;
+
+impl SolanaTransactionManager {
+    pub fn watch_balance(&self, pubkey: &str, target: Option<u64>, interval: u64) -> Result<()> {
+        let pubkey = Pubkey::from_str(pubkey)
+            .map_err(|e| TransferError::Config(format!("Invalid pubkey: {}", e)))?;
+        let mut last = self.get_balance(&pubkey)?;
+        println!("{} の残高: {}", pubkey, format_sol(last));
+
+        loop {
+            if matches!(target, Some(target) if last >= target) {
+                println!("目標残高に到達しました: {}", format_sol(last));
+                return Ok(());
+            }
+
+            std::thread::sleep(Duration::from_secs(interval));
+
+            let balance = self.get_balance(&pubkey)?;
+            if balance != last {
+                let (sign, delta) = if balance > last {
+                    ("+", balance - last)
+                } else {
+                    ("-", last - balance)
+                };
+                println!(
+                    "残高が変わりました: {} ({}{})",
+                    format_sol(balance),
+                    sign,
+                    format_sol(delta)
+                );
+                last = balance;
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::time::Duration;
+
+pub fn post_json<T: Serialize>(url: &str, body: &T, timeout: Duration) -> Result<StatusCode> {
+    tokio::task::block_in_place(|| {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()?;
+        let response = client
+            .post(url)
+            .json(body)
+            .send()
+            .map_err(|e| anyhow!("Webhook request to {} failed: {}", url, e))?;
+        Ok(response.status())
+    })
+}
+
+// For notifications that should not hold up the caller; the body is owned so the request can
+// run on a spawned task.
+pub async fn post_json_async(
+    url: &str,
+    body: serde_json::Value,
+    timeout: Duration,
+) -> Result<StatusCode> {
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let response = client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| anyhow!("Webhook request to {} failed: {}", url, e))?;
+    Ok(response.status())
+}
+
+pub fn get_text(url: &str, authorization: Option<&str>, timeout: Duration) -> Result<String> {
+    tokio::task::block_in_place(|| {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()?;
+        let mut request = client.get(url);
+        if let Some(authorization) = authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        let response = request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("Request to {} failed: {}", url, e))?;
+        Ok(response.text()?)
+    })
+}
@@ -0,0 +1,243 @@
+//! End-to-end transfers against a local `solana-test-validator`.
+//!
+//! These tests need the Solana CLI tools on PATH and are ignored by default:
+//!
+//!     cargo test --test test_validator -- --ignored --test-threads=1
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::{program_pack::Pack, system_instruction};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+struct TestValidator {
+    process: Child,
+    dir: PathBuf,
+    rpc_url: String,
+}
+
+impl TestValidator {
+    fn start(name: &str, rpc_port: u16) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("solana-transfer-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let process = Command::new("solana-test-validator")
+            .args(["--reset", "--quiet", "--ledger"])
+            .arg(dir.join("ledger"))
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &(rpc_port + 100).to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("solana-test-validator must be on PATH");
+
+        let validator = Self {
+            process,
+            dir,
+            rpc_url: format!("http://127.0.0.1:{}", rpc_port),
+        };
+
+        let client = validator.client();
+        let started = Instant::now();
+        while client.get_health().is_err() {
+            assert!(
+                started.elapsed() < Duration::from_secs(60),
+                "test validator did not become healthy"
+            );
+            std::thread::sleep(Duration::from_millis(500));
+        }
+
+        validator
+    }
+
+    fn client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed())
+    }
+
+    fn airdrop(&self, pubkey: &Pubkey, lamports: u64) {
+        let client = self.client();
+        let signature = client.request_airdrop(pubkey, lamports).unwrap();
+        let started = Instant::now();
+        while !client.confirm_transaction(&signature).unwrap() {
+            assert!(
+                started.elapsed() < Duration::from_secs(30),
+                "airdrop timed out"
+            );
+            std::thread::sleep(Duration::from_millis(500));
+        }
+    }
+
+    fn write_config(
+        &self,
+        sender: &Keypair,
+        receiver: &Pubkey,
+        amount: u64,
+        extra: &str,
+    ) -> PathBuf {
+        let path = self.dir.join("config.toml");
+        std::fs::write(
+            &path,
+            format!(
+                "[network]\n\
+                 rpc_url = \"{}\"\n\
+                 \n\
+                 [keys]\n\
+                 sender_private_key = \"{}\"\n\
+                 receiver_public_key = \"{}\"\n\
+                 \n\
+                 [transaction]\n\
+                 amount = {}\n\
+                 min_balance = 0\n\
+                 confirmation_timeout = 60\n\
+                 {}",
+                self.rpc_url,
+                sender.to_base58_string(),
+                receiver,
+                amount,
+                extra
+            ),
+        )
+        .unwrap();
+        path
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn run_transfer(dir: &Path, config: &Path) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-transfer"))
+        .arg("--config")
+        .arg(config)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "transfer failed:\n{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn printed_signature(output: &Output) -> Signature {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .find(|line| line.starts_with("TX成功!: "))
+        .expect("success line in output");
+    Signature::from_str(line.trim_start_matches("TX成功!: ")).unwrap()
+}
+
+fn assert_confirmed(client: &RpcClient, signature: &Signature) {
+    let status = client.get_signature_statuses(&[*signature]).unwrap().value[0]
+        .clone()
+        .expect("signature status");
+    assert!(status.err.is_none());
+    assert!(status.satisfies_commitment(CommitmentConfig::confirmed()));
+}
+
+#[test]
+#[ignore = "requires solana-test-validator"]
+fn sol_transfer() {
+    let validator = TestValidator::start("sol", 18899);
+    let client = validator.client();
+    let sender = Keypair::new();
+    let receiver = Keypair::new().pubkey();
+    validator.airdrop(&sender.pubkey(), 2 * LAMPORTS_PER_SOL);
+
+    let amount = LAMPORTS_PER_SOL / 10;
+    let config = validator.write_config(&sender, &receiver, amount, "");
+    let output = run_transfer(&validator.dir, &config);
+
+    assert_confirmed(&client, &printed_signature(&output));
+    assert_eq!(client.get_balance(&receiver).unwrap(), amount);
+}
+
+#[test]
+#[ignore = "requires solana-test-validator"]
+fn token_transfer() {
+    let validator = TestValidator::start("token", 18999);
+    let client = validator.client();
+    let sender = Keypair::new();
+    let receiver = Keypair::new().pubkey();
+    let mint = Keypair::new();
+    validator.airdrop(&sender.pubkey(), 2 * LAMPORTS_PER_SOL);
+
+    let decimals = 6;
+    let supply = 1_000_000_000;
+    let sender_ata = get_associated_token_address(&sender.pubkey(), &mint.pubkey());
+    let rent = client
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+        .unwrap();
+    let setup = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &sender.pubkey(),
+                &mint.pubkey(),
+                rent,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &sender.pubkey(),
+                None,
+                decimals,
+            )
+            .unwrap(),
+            create_associated_token_account(
+                &sender.pubkey(),
+                &sender.pubkey(),
+                &mint.pubkey(),
+                &spl_token::id(),
+            ),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &sender_ata,
+                &sender.pubkey(),
+                &[],
+                supply,
+            )
+            .unwrap(),
+        ],
+        Some(&sender.pubkey()),
+        &[&sender, &mint],
+        client.get_latest_blockhash().unwrap(),
+    );
+    client.send_and_confirm_transaction(&setup).unwrap();
+
+    let amount = 250_000;
+    let config = validator.write_config(
+        &sender,
+        &receiver,
+        amount,
+        &format!("\n[token]\nmint = \"{}\"\n", mint.pubkey()),
+    );
+    let output = run_transfer(&validator.dir, &config);
+
+    assert_confirmed(&client, &printed_signature(&output));
+    let receiver_ata = get_associated_token_address(&receiver, &mint.pubkey());
+    let balance = client.get_token_account_balance(&receiver_ata).unwrap();
+    assert_eq!(balance.amount, amount.to_string());
+}